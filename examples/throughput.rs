@@ -0,0 +1,114 @@
+use std::time::{Duration, Instant};
+
+use interpreter_starter_rust::parser::Parser;
+use interpreter_starter_rust::scanner::{Lexer, Scanner};
+
+/*
+    Measures how fast source is scanned and parsed, on a Lox file given as the only
+    argument or on a generated program of about 20 MB.
+
+        cargo run --release --example throughput [file.lox]
+
+    Each stage runs a few times and the fastest run is reported.
+*/
+
+const RUNS: usize = 5;
+const GENERATED_SIZE: usize = 20 * 1024 * 1024;
+
+fn main() {
+    let source = match std::env::args().nth(1) {
+        Some(path) => std::fs::read_to_string(&path).unwrap_or_else(|error| {
+            eprintln!("Failed to read file {}: {}", path, error);
+            std::process::exit(74);
+        }),
+        None => generate(GENERATED_SIZE),
+    };
+
+    let tokens = Lexer::new(&source).count();
+    println!(
+        "{:.1} MB, {} lines, {} tokens",
+        megabytes(source.len()),
+        source.lines().count(),
+        tokens
+    );
+
+    report("lex", &source, tokens, || {
+        Lexer::new(&source).count();
+    });
+    report("lex + parse, streaming", &source, tokens, || {
+        let tokens = Lexer::new(&source).map_while(Result::ok);
+        let _ = Parser::new(tokens).parse();
+    });
+    report("lex + parse, collected first", &source, tokens, || {
+        let mut scanner = Scanner::new(source.clone());
+        scanner.scan_tokens();
+        let _ = Parser::new(scanner.tokens).parse();
+    });
+}
+
+fn report(name: &str, source: &str, tokens: usize, mut run: impl FnMut()) {
+    let fastest = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            run();
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or(Duration::ZERO);
+
+    let seconds = fastest.as_secs_f64();
+    println!(
+        "{:<30} {:>8.1} ms {:>8.1} MB/s {:>6.1} M tokens/s",
+        name,
+        seconds * 1000.0,
+        megabytes(source.len()) / seconds,
+        tokens as f64 / seconds / 1_000_000.0
+    );
+}
+
+fn megabytes(bytes: usize) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+// a program using most of the grammar, repeated with fresh names until it is big enough
+fn generate(size: usize) -> String {
+    let mut source = String::with_capacity(size + 1024);
+    let mut index = 0;
+
+    while source.len() < size {
+        source.push_str(&format!(
+            "\
+/// Accumulates values, number {index}.
+class Counter{index} < Base {{
+    init(start) {{
+        this.total = start;
+    }}
+
+    add(value) {{
+        this.total = this.total + value * 2 - (value / 4);
+        return this;
+    }}
+}}
+
+fun sum{index}(limit) {{
+    var counter = Counter{index}(0x1F);
+    for (var i = 0; i < limit and counter.total != nil; i = i + 1) {{
+        if (i >= 10 or !true) {{
+            counter.add(i);
+        }} else {{
+            counter.add(1_000.5e-1);
+        }}
+    }}
+    /* totals are printed
+       for debugging */
+    print \"sum ${{counter.total}} of \\\"{index}\\\"\";
+    return counter.total;
+}}
+
+"
+        ));
+        index += 1;
+    }
+
+    source
+}
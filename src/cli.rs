@@ -0,0 +1,376 @@
+use std::io::Read;
+
+use interpreter_starter_rust::diagnostic;
+use interpreter_starter_rust::formatter::FormatOptions;
+
+/*
+    Command line handling. Options may appear anywhere after the program name,
+    everything after "--" is taken as a file.
+*/
+
+pub const USAGE: &str = "\
+Usage: interpreter-starter-rust <command> [options] [<file>...]
+
+Commands:
+  tokenize    print the tokens of each source
+  parse       print the syntax tree of each expression
+  evaluate    print the value of each expression
+  run         run each source as a program, sharing global state
+  fmt         format each source, files are rewritten in place
+
+Options:
+  -e <code>            use <code> as a source, may be repeated
+  --format <format>    how to report errors: text (default), pretty or json
+  --color <when>       with pretty, when to color errors: auto (default), always
+                       or never, auto colors them on a terminal unless NO_COLOR
+                       is set
+  --check              with fmt, change nothing but exit with 1 if a source
+                       isn't formatted
+  --width <columns>    with fmt, the width to wrap lines at (default 80)
+  -h, --help           print this help
+  -V, --version        print the version
+
+A <file> of - reads the source from stdin. Without a command, or with run and
+no sources, an interactive session is started. Formatted stdin and -e sources
+are printed, and sources with errors are never formatted.
+";
+
+// the sysexits.h code for a command used incorrectly
+pub const USAGE_EXIT_CODE: u8 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Tokenize,
+    Parse,
+    Evaluate,
+    Run,
+    Fmt { check: bool, width: usize },
+}
+
+// How errors are reported. The terse text format is the one the reference
+// implementation and its test suite expect, pretty renders rich diagnostics and
+// json emits the documents described in the json module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Pretty,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Auto,
+    Always,
+    Never,
+}
+
+impl Color {
+    pub fn enabled(self) -> bool {
+        match self {
+            Color::Auto => diagnostic::use_colors(),
+            Color::Always => true,
+            Color::Never => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    File(String),
+    Stdin,
+    Inline(String),
+}
+
+impl Source {
+    // the name diagnostics refer to the source by
+    pub fn name(&self) -> &str {
+        match self {
+            Source::File(path) => path,
+            Source::Stdin => "<stdin>",
+            Source::Inline(_) => "<-e>",
+        }
+    }
+
+    // an empty file is an empty program, a missing one is an error
+    pub fn read(&self) -> std::io::Result<String> {
+        match self {
+            Source::File(path) => std::fs::read_to_string(path),
+            Source::Stdin => {
+                let mut contents = String::new();
+                std::io::stdin().read_to_string(&mut contents)?;
+                Ok(contents)
+            }
+            Source::Inline(code) => Ok(code.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub command: Command,
+    pub format: Format,
+    pub color: Color,
+    pub sources: Vec<Source>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Help,
+    Version,
+    Repl(Format, Color),
+    Execute(Options),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("error: {0}")]
+pub struct UsageError(String);
+
+// parses the arguments after the program name
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Action, UsageError> {
+    let mut args = args.into_iter();
+    let mut command = None;
+    let mut format = Format::Text;
+    let mut color = Color::Auto;
+    let mut sources = Vec::new();
+    let mut only_files = false;
+    let mut check = false;
+    let mut width = None;
+
+    while let Some(arg) = args.next() {
+        if only_files {
+            sources.push(Source::File(arg));
+            continue;
+        }
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Action::Help),
+            "-V" | "--version" => return Ok(Action::Version),
+            "--" => only_files = true,
+            "-" => sources.push(Source::Stdin),
+            "-e" => {
+                let code = args
+                    .next()
+                    .ok_or_else(|| UsageError("-e needs the code to run".to_string()))?;
+                sources.push(Source::Inline(code));
+            }
+            "--format" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| UsageError("--format needs a value".to_string()))?;
+                format = parse_format(&value)?;
+            }
+            _ if arg.starts_with("--format=") => {
+                format = parse_format(&arg["--format=".len()..])?;
+            }
+            "--color" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| UsageError("--color needs a value".to_string()))?;
+                color = parse_color(&value)?;
+            }
+            _ if arg.starts_with("--color=") => {
+                color = parse_color(&arg["--color=".len()..])?;
+            }
+            "--check" => check = true,
+            "--width" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| UsageError("--width needs a value".to_string()))?;
+                width = Some(parse_width(&value)?);
+            }
+            _ if arg.starts_with("--width=") => {
+                width = Some(parse_width(&arg["--width=".len()..])?);
+            }
+            _ if arg.starts_with('-') => {
+                return Err(UsageError(format!("unknown option '{}'", arg)));
+            }
+            _ if command.is_none() => command = Some(parse_command(&arg)?),
+            _ => sources.push(Source::File(arg)),
+        }
+    }
+
+    // without anything to run, an interactive session is started
+    let command = match command {
+        Some(Command::Fmt { .. }) => Command::Fmt {
+            check,
+            width: width.unwrap_or(FormatOptions::default().width),
+        },
+        _ if check || width.is_some() => {
+            return Err(UsageError(
+                "--check and --width only apply to fmt".to_string(),
+            ))
+        }
+        None | Some(Command::Run) if sources.is_empty() => return Ok(Action::Repl(format, color)),
+        None => return Err(UsageError("no command given".to_string())),
+        Some(command) => command,
+    };
+    if sources.is_empty() {
+        return Err(UsageError("no source given".to_string()));
+    }
+
+    Ok(Action::Execute(Options {
+        command,
+        format,
+        color,
+        sources,
+    }))
+}
+
+fn parse_command(command: &str) -> Result<Command, UsageError> {
+    match command {
+        "tokenize" => Ok(Command::Tokenize),
+        "parse" => Ok(Command::Parse),
+        "evaluate" => Ok(Command::Evaluate),
+        "run" => Ok(Command::Run),
+        // the options are filled in once all arguments are read
+        "fmt" => Ok(Command::Fmt {
+            check: false,
+            width: 0,
+        }),
+        _ => Err(UsageError(format!("unknown command '{}'", command))),
+    }
+}
+
+fn parse_width(width: &str) -> Result<usize, UsageError> {
+    match width.parse() {
+        Ok(width) if width > 0 => Ok(width),
+        _ => Err(UsageError(format!(
+            "invalid width '{}', expected a positive number",
+            width
+        ))),
+    }
+}
+
+fn parse_format(format: &str) -> Result<Format, UsageError> {
+    match format {
+        "text" => Ok(Format::Text),
+        "pretty" => Ok(Format::Pretty),
+        "json" => Ok(Format::Json),
+        _ => Err(UsageError(format!(
+            "unknown format '{}', expected one of: text, pretty, json",
+            format
+        ))),
+    }
+}
+
+fn parse_color(color: &str) -> Result<Color, UsageError> {
+    match color {
+        "auto" => Ok(Color::Auto),
+        "always" => Ok(Color::Always),
+        "never" => Ok(Color::Never),
+        _ => Err(UsageError(format!(
+            "unknown color '{}', expected one of: auto, always, never",
+            color
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Action, UsageError> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_sources_and_options() {
+        assert_eq!(
+            parse(&["run", "a.lox", "--format", "json", "-", "-e", "print 1;", "--", "-b.lox"]),
+            Ok(Action::Execute(Options {
+                command: Command::Run,
+                format: Format::Json,
+                color: Color::Auto,
+                sources: vec![
+                    Source::File("a.lox".to_string()),
+                    Source::Stdin,
+                    Source::Inline("print 1;".to_string()),
+                    Source::File("-b.lox".to_string()),
+                ],
+            }))
+        );
+        assert_eq!(
+            parse(&["--format=pretty", "tokenize", "a.lox", "--color", "never"]),
+            Ok(Action::Execute(Options {
+                command: Command::Tokenize,
+                format: Format::Pretty,
+                color: Color::Never,
+                sources: vec![Source::File("a.lox".to_string())],
+            }))
+        );
+    }
+
+    #[test]
+    fn test_fmt_options() {
+        assert_eq!(
+            parse(&["--check", "fmt", "a.lox", "--width=100"]),
+            Ok(Action::Execute(Options {
+                command: Command::Fmt {
+                    check: true,
+                    width: 100,
+                },
+                format: Format::Text,
+                color: Color::Auto,
+                sources: vec![Source::File("a.lox".to_string())],
+            }))
+        );
+        assert_eq!(
+            parse(&["fmt", "-"]),
+            Ok(Action::Execute(Options {
+                command: Command::Fmt {
+                    check: false,
+                    width: 80,
+                },
+                format: Format::Text,
+                color: Color::Auto,
+                sources: vec![Source::Stdin],
+            }))
+        );
+    }
+
+    #[test]
+    fn test_repl_without_sources() {
+        assert_eq!(parse(&[]), Ok(Action::Repl(Format::Text, Color::Auto)));
+        assert_eq!(
+            parse(&["run", "--format", "pretty", "--color=always"]),
+            Ok(Action::Repl(Format::Pretty, Color::Always))
+        );
+    }
+
+    #[test]
+    fn test_help_and_version() {
+        assert_eq!(parse(&["run", "--help"]), Ok(Action::Help));
+        assert_eq!(parse(&["-V"]), Ok(Action::Version));
+    }
+
+    #[test]
+    fn test_usage_errors() {
+        let message = |args: &[&str]| parse(args).unwrap_err().to_string();
+
+        assert_eq!(message(&["a.lox"]), "error: unknown command 'a.lox'");
+        assert_eq!(message(&["-e", "1"]), "error: no command given");
+        assert_eq!(message(&["tokenize"]), "error: no source given");
+        assert_eq!(
+            message(&["compile", "a.lox"]),
+            "error: unknown command 'compile'"
+        );
+        assert_eq!(message(&["run", "-x"]), "error: unknown option '-x'");
+        assert_eq!(message(&["run", "-e"]), "error: -e needs the code to run");
+        assert_eq!(
+            message(&["run", "a.lox", "--check"]),
+            "error: --check and --width only apply to fmt"
+        );
+        assert_eq!(
+            message(&["fmt", "a.lox", "--width", "0"]),
+            "error: invalid width '0', expected a positive number"
+        );
+        assert_eq!(
+            message(&["run", "a.lox", "--format", "xml"]),
+            "error: unknown format 'xml', expected one of: text, pretty, json"
+        );
+        assert_eq!(
+            message(&["run", "a.lox", "--color", "yes"]),
+            "error: unknown color 'yes', expected one of: auto, always, never"
+        );
+    }
+}
@@ -0,0 +1,388 @@
+use std::io::IsTerminal;
+
+use crate::domain::{LineIndex, Span, TokenType};
+use crate::error::{ErrorCode, LoxError};
+use crate::interpreter::RuntimeError;
+use crate::parser::ParserError;
+use crate::resolver::ResolverError;
+use crate::scanner::ScannerError;
+
+/*
+    A Diagnostic is an error rendered for people rather than for the test suite: the
+    offending source line with the exact span underlined, secondary labels pointing at
+    related code, and notes or help text.
+
+        error[E0101]: Expect ')' after expression.
+         --> test.lox:1:13
+          |
+        1 | print (1 + 2;
+          |             ^
+          |       - opening '(' here
+*/
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+impl Label {
+    pub fn new(span: Span, message: String) -> Self {
+        Self { span, message }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub code: Option<ErrorCode>,
+    pub message: String,
+    // errors that aren't about the source, such as I/O errors, have no span
+    pub span: Option<Span>,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+// ANSI escape codes used when rendering in color
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[1;36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+// an underline drawn below a source line
+struct Annotation<'a> {
+    span: Span,
+    message: Option<&'a str>,
+    mark: char,
+    color: &'static str,
+}
+
+impl Diagnostic {
+    pub fn new(message: String, span: Span) -> Self {
+        Self {
+            code: None,
+            message,
+            span: Some(span),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn without_span(message: String) -> Self {
+        Self {
+            code: None,
+            message,
+            span: None,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn with_code(mut self, code: ErrorCode) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: String) -> Self {
+        self.labels.push(Label::new(span, message));
+        self
+    }
+
+    pub fn with_note(mut self, note: String) -> Self {
+        self.notes.push(note);
+        self
+    }
+
+    pub fn with_help(mut self, help: String) -> Self {
+        self.help = Some(help);
+        self
+    }
+
+    pub fn render(&self, source: &str, index: &LineIndex, path: &str, colored: bool) -> String {
+        let paint = |color: &str, text: &str| {
+            if colored {
+                format!("{}{}{}", color, text, RESET)
+            } else {
+                text.to_string()
+            }
+        };
+
+        let severity = match self.code {
+            Some(code) => format!("error[{}]", code),
+            None => "error".to_string(),
+        };
+        let mut output = format!(
+            "{}: {}\n",
+            paint(RED, &severity),
+            paint(BOLD, &self.message)
+        );
+        let Some(span) = self.span else {
+            self.render_notes(&mut output, 0, &paint);
+            return output;
+        };
+
+        let span = visible_span(span, source);
+        let (line, column) = index.line_column(source, span.start);
+
+        let mut annotations = vec![Annotation {
+            span,
+            message: None,
+            mark: '^',
+            color: RED,
+        }];
+        for label in &self.labels {
+            annotations.push(Annotation {
+                span: visible_span(label.span, source),
+                message: Some(&label.message),
+                mark: '-',
+                color: BLUE,
+            });
+        }
+        // stable, so the primary underline stays first on its line
+        annotations.sort_by_key(|annotation| index.line_column(source, annotation.span.start).0);
+
+        let last_line = annotations
+            .iter()
+            .map(|annotation| index.line_column(source, annotation.span.start).0)
+            .max()
+            .unwrap_or(line);
+        let width = last_line.to_string().len();
+        let gutter = paint(BLUE, &format!("{} |", " ".repeat(width)));
+
+        output.push_str(&format!(
+            "{}{} {}:{}:{}\n{}\n",
+            " ".repeat(width),
+            paint(BLUE, "-->"),
+            path,
+            line,
+            column,
+            gutter
+        ));
+
+        let mut previous_line = None;
+        for annotation in &annotations {
+            let (line, column) = index.line_column(source, annotation.span.start);
+            let line_span = index.line_span(source, line);
+            let text = &source[line_span.start..line_span.end];
+
+            if previous_line != Some(line) {
+                if previous_line.is_some_and(|previous| previous + 1 < line) {
+                    output.push_str(&paint(BLUE, "...\n"));
+                }
+                output.push_str(&format!(
+                    "{} {}\n",
+                    paint(BLUE, &format!("{:>width$} |", line)),
+                    text
+                ));
+                previous_line = Some(line);
+            }
+
+            // tabs are copied so the underline lines up however wide they are drawn
+            let indent: String = text
+                .chars()
+                .take(column as usize - 1)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+
+            // spans running past the end of the line are cut off there
+            let end = annotation
+                .span
+                .end
+                .min(line_span.end)
+                .max(annotation.span.start);
+            let length = source
+                .get(annotation.span.start..end)
+                .map_or(0, |underlined| underlined.chars().count())
+                .max(1);
+
+            let mut underline = annotation.mark.to_string().repeat(length);
+            if let Some(message) = annotation.message {
+                underline = format!("{} {}", underline, message);
+            }
+            output.push_str(&format!(
+                "{} {}{}\n",
+                gutter,
+                indent,
+                paint(annotation.color, &underline)
+            ));
+        }
+
+        self.render_notes(&mut output, width, &paint);
+        output
+    }
+
+    // notes and help go below the source lines, lined up with their gutter
+    fn render_notes(
+        &self,
+        output: &mut String,
+        width: usize,
+        paint: &dyn Fn(&str, &str) -> String,
+    ) {
+        for note in &self.notes {
+            output.push_str(&format!(
+                "{} {}: {}\n",
+                paint(BLUE, &format!("{} =", " ".repeat(width))),
+                paint(BOLD, "note"),
+                note
+            ));
+        }
+        if let Some(help) = &self.help {
+            output.push_str(&format!(
+                "{} {}: {}\n",
+                paint(BLUE, &format!("{} =", " ".repeat(width))),
+                paint(CYAN, "help"),
+                help
+            ));
+        }
+    }
+}
+
+// An error at the end of the file points just past the last visible character
+// instead of at an empty line after the final newline.
+fn visible_span(span: Span, source: &str) -> Span {
+    let content_end = source.trim_end().len();
+    if span.is_empty() && span.start > content_end {
+        return Span::new(content_end, content_end);
+    }
+
+    span
+}
+
+// colors are used when diagnostics go to a terminal, unless NO_COLOR is set
+pub fn use_colors() -> bool {
+    std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+impl From<&ScannerError> for Diagnostic {
+    fn from(error: &ScannerError) -> Self {
+        Diagnostic::new(error.message.clone(), error.span).with_code(error.code)
+    }
+}
+
+impl From<&ParserError> for Diagnostic {
+    fn from(error: &ParserError) -> Self {
+        let mut diagnostic =
+            Diagnostic::new(error.message.clone(), error.token.span).with_code(error.code);
+        diagnostic.labels = error.labels.clone();
+        diagnostic.help = error.help.clone();
+
+        if error.token.token_type == TokenType::Eof {
+            diagnostic =
+                diagnostic.with_note("the file ended before this was complete".to_string());
+        }
+        diagnostic
+    }
+}
+
+impl From<&ResolverError> for Diagnostic {
+    fn from(error: &ResolverError) -> Self {
+        Diagnostic::new(error.message.clone(), error.token.span).with_code(error.code)
+    }
+}
+
+impl From<&RuntimeError> for Diagnostic {
+    fn from(error: &RuntimeError) -> Self {
+        Diagnostic::new(error.message.clone(), error.token.span).with_code(error.code)
+    }
+}
+
+impl From<&LoxError> for Diagnostic {
+    fn from(error: &LoxError) -> Self {
+        match error {
+            LoxError::Lex(error) => Diagnostic::from(error),
+            LoxError::Parse(error) => Diagnostic::from(error),
+            LoxError::Resolve(error) => Diagnostic::from(error),
+            LoxError::Runtime(error) => Diagnostic::from(error),
+            LoxError::Io { .. } => {
+                Diagnostic::without_span(error.to_string()).with_code(error.code())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+
+    #[test]
+    fn test_render_with_label_and_help() {
+        let source = "var a = 1;\nprint (a + 2;\n";
+        let diagnostic = Diagnostic::new(
+            "Expect ')' after expression.".to_string(),
+            Span::new(23, 24),
+        )
+        .with_label(Span::new(17, 18), "opening '(' here".to_string())
+        .with_help("add the missing ')'".to_string());
+
+        assert_eq!(
+            diagnostic.render(source, &LineIndex::new(source), "test.lox", false),
+            "error: Expect ')' after expression.
+ --> test.lox:2:13
+  |
+2 | print (a + 2;
+  |             ^
+  |       - opening '(' here
+  = help: add the missing ')'
+"
+        );
+    }
+
+    #[test]
+    fn test_render_across_lines() {
+        let source = "{\n\tprint \"é\" + nil;\n";
+        let diagnostic = Diagnostic::new("Expect '}' after block.".to_string(), Span::new(21, 21))
+            .with_label(Span::new(0, 1), "opening '{' here".to_string());
+
+        assert_eq!(
+            diagnostic.render(source, &LineIndex::new(source), "test.lox", false),
+            "error: Expect '}' after block.
+ --> test.lox:2:18
+  |
+1 | {
+  | - opening '{' here
+2 | \tprint \"é\" + nil;
+  | \t                ^
+"
+        );
+    }
+
+    #[test]
+    fn test_render_in_color() {
+        let diagnostic = Diagnostic::new("Unexpected character: @".to_string(), Span::new(0, 1));
+        let rendered = diagnostic.render("@", &LineIndex::new("@"), "test.lox", true);
+
+        assert!(rendered.starts_with("\x1b[1;31merror\x1b[0m: \x1b[1mUnexpected character: @"));
+        assert!(rendered.contains("\x1b[1;31m^\x1b[0m"));
+    }
+
+    #[test]
+    fn test_render_with_code() {
+        let source = "print @;";
+        let error = Interpreter::new().run(source).unwrap_err().remove(0);
+
+        assert_eq!(
+            Diagnostic::from(&error).render(source, &LineIndex::new(source), "test.lox", false),
+            "error[E0001]: Unexpected character: @
+ --> test.lox:1:7
+  |
+1 | print @;
+  |       ^
+"
+        );
+    }
+
+    #[test]
+    fn test_render_without_span() {
+        let diagnostic = Diagnostic::without_span("Failed to read file a.lox".to_string())
+            .with_help("check the path".to_string());
+
+        assert_eq!(
+            diagnostic.render("", &LineIndex::new(""), "a.lox", false),
+            "error: Failed to read file a.lox\n = help: check the path\n"
+        );
+    }
+}
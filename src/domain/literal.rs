@@ -1,6 +1,5 @@
 #[derive(Debug, Clone, PartialEq)]
 pub enum Literal {
-    Identifier(String),
     String(String),
     Number(f64),
     Boolean(bool),
@@ -10,7 +9,6 @@ pub enum Literal {
 impl std::fmt::Display for Literal {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Literal::Identifier(_) => write!(f, "null"),
             Literal::String(string) => write!(f, "{}", string),
             Literal::Number(number) => write!(f, "{:?}", number),
             Literal::Boolean(boolean) => write!(f, "{}", boolean),
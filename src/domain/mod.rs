@@ -1,6 +1,12 @@
+pub mod span;
+pub use span::{LineIndex, Span};
+
 pub mod token;
 pub use token::Token;
 
+pub mod trivia;
+pub use trivia::{Trivia, TriviaKind};
+
 pub mod token_type;
 pub use token_type::TokenType;
 
@@ -9,3 +15,9 @@ pub use literal::Literal;
 
 pub mod expression;
 pub use expression::Expression;
+
+pub mod statement;
+pub use statement::Statement;
+
+pub mod value;
+pub use value::Value;
@@ -0,0 +1,124 @@
+/*
+    A Span is a range of byte offsets into the source, start inclusive and end exclusive.
+    Lines and columns are derived from it with a LineIndex built over the same source.
+*/
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    // the smallest span covering both self and other
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+// Maps byte offsets to 1-based lines and columns, columns are counted in characters.
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(source: &str) -> Self {
+        let mut line_starts = vec![0];
+        for (offset, byte) in source.bytes().enumerate() {
+            if byte == b'\n' {
+                line_starts.push(offset + 1);
+            }
+        }
+
+        Self { line_starts }
+    }
+
+    pub fn line_column(&self, source: &str, offset: usize) -> (u32, u32) {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+
+        let line_start = self.line_starts[line];
+        let offset = offset.min(source.len());
+        let column = source
+            .get(line_start..offset)
+            .map_or(offset - line_start, |text| text.chars().count());
+
+        (line as u32 + 1, column as u32 + 1)
+    }
+
+    // the byte range of the given 1-based line, without its line terminator
+    pub fn line_span(&self, source: &str, line: u32) -> Span {
+        let index = (line as usize).saturating_sub(1);
+        let start = self.line_starts.get(index).copied().unwrap_or(source.len());
+        let end = self
+            .line_starts
+            .get(index + 1)
+            .map_or(source.len(), |next| next - 1);
+
+        let end = if end > start && source.as_bytes()[end - 1] == b'\r' {
+            end - 1
+        } else {
+            end
+        };
+
+        Span::new(start, end.max(start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_column() {
+        let source = "var a;\nprint \"é\" + a;\n";
+        let index = LineIndex::new(source);
+
+        assert_eq!(index.line_column(source, 0), (1, 1));
+        assert_eq!(index.line_column(source, 4), (1, 5));
+        assert_eq!(index.line_column(source, 7), (2, 1));
+        // "é" is two bytes but a single column
+        let plus = source.find('+').unwrap();
+        assert_eq!(index.line_column(source, plus), (2, 11));
+        assert_eq!(index.line_column(source, source.len()), (3, 1));
+    }
+
+    #[test]
+    fn test_line_span() {
+        let source = "first\r\nsecond\nthird";
+        let index = LineIndex::new(source);
+
+        assert_eq!(
+            &source[index.line_span(source, 1).start..index.line_span(source, 1).end],
+            "first"
+        );
+        assert_eq!(index.line_span(source, 2), Span::new(7, 13));
+        assert_eq!(index.line_span(source, 3), Span::new(14, 19));
+    }
+
+    #[test]
+    fn test_to_covers_both_spans() {
+        assert_eq!(Span::new(4, 6).to(Span::new(1, 2)), Span::new(1, 6));
+    }
+}
@@ -0,0 +1,167 @@
+use std::rc::Rc;
+
+use super::{token::Token, Expression};
+
+/*
+
+The Statement enum covers the statement grammar
+
+program        → declaration* EOF ;
+declaration    → classDecl
+               | funDecl
+               | varDecl
+               | statement ;
+classDecl      → "class" IDENTIFIER ( "<" IDENTIFIER )?
+                 "{" function* "}" ;
+funDecl        → "fun" function ;
+function       → IDENTIFIER "(" parameters? ")" block ;
+parameters     → IDENTIFIER ( "," IDENTIFIER )* ;
+varDecl        → "var" IDENTIFIER ( "=" expression )? ";" ;
+statement      → exprStmt
+               | forStmt
+               | ifStmt
+               | printStmt
+               | returnStmt
+               | whileStmt
+               | block ;
+forStmt        → "for" "(" ( varDecl | exprStmt | ";" )
+                 expression? ";"
+                 expression? ")" statement ;
+ifStmt         → "if" "(" expression ")" statement
+                 ( "else" statement )? ;
+whileStmt      → "while" "(" expression ")" statement ;
+block          → "{" declaration* "}" ;
+exprStmt       → expression ";" ;
+printStmt      → "print" expression ";" ;
+returnStmt     → "return" expression? ";" ;
+
+*/
+
+// A syntactically valid program, only produced by a parse without errors.
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub statements: Vec<Statement>,
+}
+
+impl Program {
+    pub fn new(statements: Vec<Statement>) -> Self {
+        Self { statements }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FunctionDeclaration {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Vec<Statement>,
+    // the `///` comments written before the declaration
+    pub doc: Option<String>,
+}
+
+impl FunctionDeclaration {
+    pub fn new(name: Token, params: Vec<Token>, body: Vec<Statement>, doc: Option<String>) -> Self {
+        Self {
+            name,
+            params,
+            body,
+            doc,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Statement {
+    Block(Vec<Statement>),
+    Class {
+        name: Token,
+        // always an Expression::Variable
+        superclass: Option<Expression>,
+        methods: Vec<Rc<FunctionDeclaration>>,
+        doc: Option<String>,
+    },
+    Expression(Expression),
+    // shared so that every closure created from it can refer to the declaration
+    Function(Rc<FunctionDeclaration>),
+    If {
+        condition: Expression,
+        then_branch: Box<Statement>,
+        else_branch: Option<Box<Statement>>,
+    },
+    Print(Expression),
+    Return {
+        keyword: Token,
+        value: Option<Expression>,
+    },
+    Var {
+        name: Token,
+        initializer: Option<Expression>,
+        doc: Option<String>,
+    },
+    While {
+        condition: Expression,
+        body: Box<Statement>,
+    },
+}
+
+impl Statement {
+    pub fn new_block(statements: Vec<Statement>) -> Self {
+        Self::Block(statements)
+    }
+
+    pub fn new_expression(expression: Expression) -> Self {
+        Self::Expression(expression)
+    }
+
+    pub fn new_class(
+        name: Token,
+        superclass: Option<Expression>,
+        methods: Vec<Rc<FunctionDeclaration>>,
+        doc: Option<String>,
+    ) -> Self {
+        Self::Class {
+            name,
+            superclass,
+            methods,
+            doc,
+        }
+    }
+
+    pub fn new_function(declaration: FunctionDeclaration) -> Self {
+        Self::Function(Rc::new(declaration))
+    }
+
+    pub fn new_if(
+        condition: Expression,
+        then_branch: Statement,
+        else_branch: Option<Statement>,
+    ) -> Self {
+        Self::If {
+            condition,
+            then_branch: Box::new(then_branch),
+            else_branch: else_branch.map(Box::new),
+        }
+    }
+
+    pub fn new_print(expression: Expression) -> Self {
+        Self::Print(expression)
+    }
+
+    pub fn new_return(keyword: Token, value: Option<Expression>) -> Self {
+        Self::Return { keyword, value }
+    }
+
+    pub fn new_var(name: Token, initializer: Option<Expression>, doc: Option<String>) -> Self {
+        Self::Var {
+            name,
+            initializer,
+            doc,
+        }
+    }
+
+    pub fn new_while(condition: Expression, body: Statement) -> Self {
+        Self::While {
+            condition,
+            body: Box::new(body),
+        }
+    }
+}
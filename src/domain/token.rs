@@ -1,29 +1,91 @@
+use std::rc::Rc;
+
 use super::literal::Literal;
+use super::span::Span;
 use super::token_type::TokenType;
+use super::trivia::{Trivia, TriviaKind};
 
 #[derive(Debug, Clone)]
 pub struct Token {
     pub token_type: TokenType,
-    pub lexeme: String,
+    // shared rather than owned, the lexer hands out one allocation per distinct
+    // lexeme and cloning a token never copies its text
+    pub lexeme: Rc<str>,
+    // what the token stands for when names are compared, for an identifier its
+    // lexeme in Normalization Form C, for anything else the lexeme itself
+    pub symbol: Rc<str>,
     pub literal: Option<Literal>,
+    // the line the lexeme ends on, which the reference implementation reports
     pub line: u32,
+    // the line and 1-based column the lexeme starts at, these differ from
+    // `line` only for tokens spanning several lines such as multi-line strings
+    pub start_line: u32,
     pub column: u32,
+    pub span: Span,
+    // doc comments and the like found between the previous token and this one
+    // boxed rather than a Vec to keep tokens, and the errors holding them, small
+    pub leading_trivia: Box<[Trivia]>,
 }
 
 impl Token {
     pub fn new(
         token_type: TokenType,
-        lexeme: String,
+        lexeme: impl Into<Rc<str>>,
         literal: Option<Literal>,
         line: u32,
         column: u32,
+        span: Span,
     ) -> Self {
+        let lexeme = lexeme.into();
         Self {
             token_type,
+            symbol: Rc::clone(&lexeme),
             lexeme,
             literal,
             line,
+            start_line: line,
             column,
+            span,
+            leading_trivia: Box::default(),
+        }
+    }
+
+    pub fn with_symbol(mut self, symbol: Rc<str>) -> Self {
+        self.symbol = symbol;
+        self
+    }
+
+    pub fn with_start_line(mut self, start_line: u32) -> Self {
+        self.start_line = start_line;
+        self
+    }
+
+    pub fn with_leading_trivia(mut self, leading_trivia: Vec<Trivia>) -> Self {
+        self.leading_trivia = leading_trivia.into_boxed_slice();
+        self
+    }
+
+    // the doc comments right before this token, one line each
+    pub fn doc_comment(&self) -> Option<String> {
+        let lines: Vec<&str> = self
+            .leading_trivia
+            .iter()
+            .filter(|trivia| trivia.kind == TriviaKind::DocComment)
+            .map(Trivia::doc_text)
+            .collect();
+
+        if lines.is_empty() {
+            None
+        } else {
+            Some(lines.join("\n"))
+        }
+    }
+
+    // where an error was found, in the reference implementation's wording
+    pub fn error_location(&self) -> String {
+        match self.token_type {
+            TokenType::Eof => " at end".to_string(),
+            _ => format!(" at '{}'", self.lexeme),
         }
     }
 }
@@ -32,14 +94,9 @@ impl std::fmt::Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if let Some(literal) = &self.literal {
             match literal {
-                Literal::Boolean(_) => {
-                    return write!(f, "{} {} null", self.token_type, self.lexeme)
-                }
-                Literal::Identifier(_) => {
-                    return write!(f, "{} {} null", self.token_type, self.lexeme)
-                }
-                Literal::Nil => return write!(f, "{} {} null", self.token_type, literal),
-                _ => return write!(f, "{} {} {}", self.token_type, self.lexeme, literal),
+                Literal::Boolean(_) => write!(f, "{} {} null", self.token_type, self.lexeme),
+                Literal::Nil => write!(f, "{} {} null", self.token_type, literal),
+                _ => write!(f, "{} {} {}", self.token_type, self.lexeme, literal),
             }
         } else {
             write!(f, "{} {} null", self.token_type, self.lexeme)
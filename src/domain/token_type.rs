@@ -26,6 +26,8 @@ pub enum TokenType {
     // Literals.
     Identifier,
     String,
+    // the part of an interpolated string up to a `${`
+    Interpolation,
     Number,
     // Keywords.
     And,
@@ -71,6 +73,7 @@ impl fmt::Display for TokenType {
             TokenType::LessEqual => "LESS_EQUAL",
             TokenType::Identifier => "IDENTIFIER",
             TokenType::String => "STRING",
+            TokenType::Interpolation => "INTERPOLATION",
             TokenType::Number => "NUMBER",
             TokenType::And => "AND",
             TokenType::Class => "CLASS",
@@ -89,7 +92,6 @@ impl fmt::Display for TokenType {
             TokenType::Var => "VAR",
             TokenType::While => "WHILE",
             TokenType::Eof => "EOF",
-            _ => panic!("Unknown token type"),
         };
         write!(f, "{}", token_representation)
     }
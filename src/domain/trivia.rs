@@ -0,0 +1,40 @@
+use super::span::Span;
+
+/*
+    Trivia is source text that carries no meaning for the program but is kept on the
+    token that follows it, like doc comments, so tooling can get at it.
+
+    Only doc comments are kept by default. A lexer keeping all trivia accounts for
+    every byte of the source, which is what the syntax tree is built from.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    // a `///` comment, documenting the declaration after it
+    DocComment,
+    LineComment,
+    BlockComment,
+    // a run of spaces, tabs and newlines
+    Whitespace,
+    // text that failed to scan, already reported as a ScannerError
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub span: Span,
+}
+
+impl Trivia {
+    pub fn new(kind: TriviaKind, text: String, span: Span) -> Self {
+        Self { kind, text, span }
+    }
+
+    // the comment without its `///` marker and the single space usually after it
+    pub fn doc_text(&self) -> &str {
+        let text = self.text.trim_start_matches('/');
+        text.strip_prefix(' ').unwrap_or(text)
+    }
+}
@@ -0,0 +1,132 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::Literal;
+use crate::class::{LoxClass, LoxInstance};
+use crate::function::{LoxFunction, NativeFunction};
+
+/*
+    A Value is what an expression evaluates to at runtime.
+
+    Reference - https://craftinginterpreters.com/evaluating-expressions.html#representing-values
+*/
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    String(String),
+    Number(f64),
+    Boolean(bool),
+    Nil,
+    Function(Rc<LoxFunction>),
+    NativeFunction(Rc<NativeFunction>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+}
+
+impl Value {
+    // false and nil are falsey, and everything else is truthy.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Nil => false,
+            Value::Boolean(boolean) => *boolean,
+            _ => true,
+        }
+    }
+}
+
+// functions, classes and instances are only equal to themselves
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::String(left), Value::String(right)) => left == right,
+            (Value::Number(left), Value::Number(right)) => left == right,
+            (Value::Boolean(left), Value::Boolean(right)) => left == right,
+            (Value::Nil, Value::Nil) => true,
+            (Value::Function(left), Value::Function(right)) => Rc::ptr_eq(left, right),
+            (Value::NativeFunction(left), Value::NativeFunction(right)) => Rc::ptr_eq(left, right),
+            (Value::Class(left), Value::Class(right)) => Rc::ptr_eq(left, right),
+            (Value::Instance(left), Value::Instance(right)) => Rc::ptr_eq(left, right),
+            _ => false,
+        }
+    }
+}
+
+impl From<Literal> for Value {
+    fn from(literal: Literal) -> Self {
+        match literal {
+            Literal::String(string) => Value::String(string),
+            Literal::Number(number) => Value::Number(number),
+            Literal::Boolean(boolean) => Value::Boolean(boolean),
+            Literal::Nil => Value::Nil,
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::String(string) => write!(f, "{}", string),
+            Value::Number(number) => write_number(f, *number),
+            Value::Boolean(boolean) => write!(f, "{}", boolean),
+            Value::Nil => write!(f, "nil"),
+            Value::Function(function) => write!(f, "{}", function),
+            Value::NativeFunction(function) => write!(f, "{}", function),
+            Value::Class(class) => write!(f, "{}", class),
+            Value::Instance(instance) => write!(f, "{}", instance.borrow()),
+        }
+    }
+}
+
+// Numbers are printed as the reference implementation prints them, which is Java's
+// Double.toString without a trailing ".0": plain decimals from 10^-3 up to 10^7 and
+// scientific notation such as 1.0E21 outside of that range.
+fn write_number(f: &mut std::fmt::Formatter<'_>, number: f64) -> std::fmt::Result {
+    if number.is_nan() {
+        return write!(f, "NaN");
+    }
+    if number.is_infinite() {
+        return write!(f, "{}Infinity", if number < 0.0 { "-" } else { "" });
+    }
+
+    let magnitude = number.abs();
+    if magnitude == 0.0 || (1e-3..1e7).contains(&magnitude) {
+        // Rust prints the same shortest digits, and integers without the ".0"
+        return write!(f, "{}", number);
+    }
+
+    let scientific = format!("{:e}", number);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    if mantissa.contains('.') {
+        write!(f, "{}E{}", mantissa, exponent)
+    } else {
+        write!(f, "{}.0E{}", mantissa, exponent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numbers_print_like_the_reference_implementation() {
+        let cases = [
+            (1.0, "1"),
+            (-0.0, "-0"),
+            (2.5, "2.5"),
+            (0.001, "0.001"),
+            (1234567.5, "1234567.5"),
+            (1e7, "1.0E7"),
+            (1e21, "1.0E21"),
+            (-1.5e300, "-1.5E300"),
+            (1.0e-4, "1.0E-4"),
+            (1.2345e-7, "1.2345E-7"),
+            (f64::NAN, "NaN"),
+            (f64::INFINITY, "Infinity"),
+            (f64::NEG_INFINITY, "-Infinity"),
+        ];
+
+        for (number, expected) in cases {
+            assert_eq!(Value::Number(number).to_string(), expected);
+        }
+    }
+}
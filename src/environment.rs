@@ -0,0 +1,164 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::domain::{token::Token, Value};
+use crate::error::ErrorCode;
+use crate::interpreter::RuntimeError;
+
+/*
+    An Environment stores the bindings of one lexical scope and points to the
+    scope enclosing it. Lookups and assignments walk outward through the chain
+    until they find the scope that defines the variable.
+
+    Reference - https://craftinginterpreters.com/statements-and-state.html#environments
+*/
+
+#[derive(Debug, Default)]
+pub struct Environment {
+    values: HashMap<Rc<str>, Value>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self {
+            values: HashMap::new(),
+            enclosing: None,
+        }
+    }
+
+    pub fn new_enclosed(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    // redefining an existing variable in the same scope is allowed
+    pub fn define(&mut self, name: Rc<str>, value: Value) {
+        self.values.insert(name, value);
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Value, RuntimeError> {
+        if let Some(value) = self.values.get(&name.symbol) {
+            return Ok(value.clone());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get(name),
+            None => Err(Self::undefined_variable(name)),
+        }
+    }
+
+    // looks up a binding exactly `distance` scopes out, as recorded by the resolver
+    pub fn get_at(&self, distance: usize, name: &str) -> Option<Value> {
+        if distance == 0 {
+            return self.values.get(name).cloned();
+        }
+
+        self.enclosing
+            .as_ref()
+            .and_then(|enclosing| enclosing.borrow().get_at(distance - 1, name))
+    }
+
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), RuntimeError> {
+        if let Some(slot) = self.values.get_mut(&name.symbol) {
+            *slot = value;
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(Self::undefined_variable(name)),
+        }
+    }
+
+    pub fn assign_at(
+        &mut self,
+        distance: usize,
+        name: &Token,
+        value: Value,
+    ) -> Result<(), RuntimeError> {
+        if distance == 0 {
+            self.values.insert(name.symbol.clone(), value);
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign_at(distance - 1, name, value),
+            None => Err(Self::undefined_variable(name)),
+        }
+    }
+
+    fn undefined_variable(name: &Token) -> RuntimeError {
+        RuntimeError::new(
+            ErrorCode::UndefinedVariable,
+            format!("Undefined variable '{}'.", name.lexeme),
+            name.clone(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Span, TokenType};
+
+    fn identifier(name: &str) -> Token {
+        Token::new(
+            TokenType::Identifier,
+            name.to_string(),
+            None,
+            1,
+            1,
+            Span::default(),
+        )
+    }
+
+    #[test]
+    fn test_get_walks_enclosing_scopes() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals.borrow_mut().define("a".into(), Value::Number(1.0));
+
+        let local = Environment::new_enclosed(Rc::clone(&globals));
+        assert_eq!(local.get(&identifier("a")).unwrap(), Value::Number(1.0));
+        assert_eq!(
+            local.get(&identifier("b")).unwrap_err().message,
+            "Undefined variable 'b'."
+        );
+    }
+
+    #[test]
+    fn test_assign_updates_defining_scope() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals.borrow_mut().define("a".into(), Value::Number(1.0));
+
+        let mut local = Environment::new_enclosed(Rc::clone(&globals));
+        local.assign(&identifier("a"), Value::Number(2.0)).unwrap();
+
+        assert_eq!(
+            globals.borrow().get(&identifier("a")).unwrap(),
+            Value::Number(2.0)
+        );
+        assert!(local.assign(&identifier("b"), Value::Nil).is_err());
+    }
+
+    #[test]
+    fn test_get_at_and_assign_at_skip_shadowing_scopes() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals.borrow_mut().define("a".into(), Value::Number(1.0));
+
+        let mut local = Environment::new_enclosed(Rc::clone(&globals));
+        local.define("a".into(), Value::Number(2.0));
+
+        assert_eq!(local.get_at(0, "a"), Some(Value::Number(2.0)));
+        assert_eq!(local.get_at(1, "a"), Some(Value::Number(1.0)));
+
+        local
+            .assign_at(1, &identifier("a"), Value::Number(3.0))
+            .unwrap();
+        assert_eq!(local.get_at(0, "a"), Some(Value::Number(2.0)));
+        assert_eq!(globals.borrow().get_at(0, "a"), Some(Value::Number(3.0)));
+    }
+}
@@ -0,0 +1,185 @@
+use crate::domain::Span;
+use crate::interpreter::RuntimeError;
+use crate::parser::ParserError;
+use crate::resolver::ResolverError;
+use crate::scanner::ScannerError;
+
+/*
+    LoxError is the one error type for everything that can go wrong while running a
+    program. Each stage keeps its own error struct and LoxError wraps them.
+
+    Exit codes follow the reference implementation, which takes them from sysexits.h:
+    65 (EX_DATAERR) for errors in the source, 70 (EX_SOFTWARE) for runtime errors and
+    74 (EX_IOERR) when the source can't be read.
+
+    Every kind of error also has a stable code, reported next to its message. Codes
+    are grouped by the stage that reports them, E00xx while scanning, E01xx while
+    parsing, E02xx while resolving, E03xx at runtime and E04xx for I/O, and are never
+    reused for a different kind of error.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    UnexpectedCharacter = 1,
+    UnterminatedString = 2,
+    UnterminatedBlockComment = 3,
+    UnterminatedInterpolation = 4,
+    InvalidEscape = 5,
+    InvalidNumber = 6,
+
+    ExpectedToken = 101,
+    ExpectedExpression = 102,
+    InvalidAssignmentTarget = 103,
+    TooManyParameters = 104,
+    TooManyArguments = 105,
+    NestedTooDeeply = 106,
+
+    AlreadyDeclared = 201,
+    ReadInOwnInitializer = 202,
+    TopLevelReturn = 203,
+    ReturnFromInitializer = 204,
+    ThisOutsideClass = 205,
+    SuperOutsideClass = 206,
+    SuperWithoutSuperclass = 207,
+    InheritsFromItself = 208,
+
+    OperandType = 301,
+    UndefinedVariable = 302,
+    UndefinedProperty = 303,
+    NotAnInstance = 304,
+    NotCallable = 305,
+    ArityMismatch = 306,
+    SuperclassNotAClass = 307,
+    StackOverflow = 308,
+    UnknownOperator = 309,
+
+    Io = 401,
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "E{:04}", *self as u16)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LoxError {
+    #[error(transparent)]
+    Lex(#[from] ScannerError),
+    #[error(transparent)]
+    Parse(#[from] ParserError),
+    #[error(transparent)]
+    Resolve(#[from] ResolverError),
+    #[error(transparent)]
+    Runtime(#[from] RuntimeError),
+    #[error("Failed to read file {path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+impl LoxError {
+    pub fn io(path: &str, source: std::io::Error) -> Self {
+        LoxError::Io {
+            path: path.to_string(),
+            source,
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            LoxError::Lex(error) => error.code,
+            LoxError::Parse(error) => error.code,
+            LoxError::Resolve(error) => error.code,
+            LoxError::Runtime(error) => error.code,
+            LoxError::Io { .. } => ErrorCode::Io,
+        }
+    }
+
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            LoxError::Lex(_) | LoxError::Parse(_) | LoxError::Resolve(_) => 65,
+            LoxError::Runtime(_) => 70,
+            LoxError::Io { .. } => 74,
+        }
+    }
+
+    // where in the source the error is, I/O errors have no position
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            LoxError::Lex(error) => Some(error.span),
+            LoxError::Parse(error) => Some(error.token.span),
+            LoxError::Resolve(error) => Some(error.token.span),
+            LoxError::Runtime(error) => Some(error.token.span),
+            LoxError::Io { .. } => None,
+        }
+    }
+
+    pub fn is_compile_error(&self) -> bool {
+        self.exit_code() == 65
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+
+    fn run(source: &str) -> Vec<LoxError> {
+        Interpreter::new().run(source).err().unwrap_or_default()
+    }
+
+    #[test]
+    fn test_each_stage_maps_to_its_error() {
+        let lex = run("print @;");
+        assert_eq!(lex[0].code(), ErrorCode::UnexpectedCharacter);
+        assert_eq!(lex[0].span(), Some(Span::new(6, 7)));
+
+        let parse = run("print 1 +;\nprint;");
+        assert_eq!(parse.len(), 2);
+        assert!(parse
+            .iter()
+            .all(|error| error.code() == ErrorCode::ExpectedExpression));
+
+        let resolve = run("return 1;");
+        assert_eq!(resolve[0].code(), ErrorCode::TopLevelReturn);
+        assert!(resolve[0].is_compile_error());
+
+        let runtime = run("print -\"a\";");
+        assert_eq!(runtime[0].code(), ErrorCode::OperandType);
+        assert_eq!(runtime[0].exit_code(), 70);
+        assert_eq!(
+            runtime[0].to_string(),
+            "Operand must be a number.\n[line 1]"
+        );
+    }
+
+    #[test]
+    fn test_io_error() {
+        let error = LoxError::io(
+            "missing.lox",
+            std::io::Error::from(std::io::ErrorKind::NotFound),
+        );
+
+        assert_eq!(error.code(), ErrorCode::Io);
+        assert_eq!(error.exit_code(), 74);
+        assert_eq!(error.span(), None);
+        assert!(error
+            .to_string()
+            .starts_with("Failed to read file missing.lox: "));
+    }
+
+    #[test]
+    fn test_code_display() {
+        assert_eq!(ErrorCode::UnexpectedCharacter.to_string(), "E0001");
+        assert_eq!(ErrorCode::ExpectedToken.to_string(), "E0101");
+        assert_eq!(ErrorCode::Io.to_string(), "E0401");
+    }
+
+    #[test]
+    fn test_valid_program_runs() {
+        assert!(Interpreter::new().run("var a = 1; print a + 1;").is_ok());
+    }
+}
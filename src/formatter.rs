@@ -0,0 +1,857 @@
+use std::collections::HashSet;
+
+use crate::domain::{Token, TokenType, TriviaKind};
+use crate::error::LoxError;
+use crate::syntax::{SyntaxElement, SyntaxKind, SyntaxNode, SyntaxTree};
+
+/*
+    The formatter prints a program in one canonical layout. It works on the concrete
+    syntax tree, so comments come along with the tokens they sit before, and it only
+    formats sources without scanner or parser errors.
+
+    Layout is left to a small pretty printer: the tree is turned into a document of
+    text, possible line breaks and groups, and a group is printed on one line when
+    it fits in the width and broken up otherwise.
+
+    Reference - https://homepages.inf.ed.ac.uk/wadler/papers/prettier/prettier.pdf
+*/
+
+const INDENT: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatOptions {
+    // the width long expressions are wrapped at
+    pub width: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self { width: 80 }
+    }
+}
+
+pub fn format(source: &str, options: &FormatOptions) -> Result<String, Vec<LoxError>> {
+    let tree = SyntaxTree::parse(source);
+    if tree.has_errors() {
+        return Err(tree.errors);
+    }
+
+    let document = Formatter::new(&tree.source).program(&tree.root);
+    Ok(print(&document, options.width))
+}
+
+#[derive(Debug, Clone)]
+enum Doc {
+    Text(String),
+    // a space, unless there is one already, the line is empty so far or it would
+    // follow an opening paren
+    Space,
+    // a space, or a newline when the group around it is broken
+    Line,
+    // nothing, or a newline when the group around it is broken
+    SoftLine,
+    // always a newline, so every group around it is broken
+    HardLine,
+    Indent(Vec<Doc>),
+    Group { docs: Vec<Doc>, broken: bool },
+}
+
+impl Doc {
+    fn text(text: &str) -> Doc {
+        Doc::Text(text.to_string())
+    }
+
+    fn group(docs: Vec<Doc>) -> Doc {
+        let broken = docs.iter().any(Doc::breaks);
+        Doc::Group { docs, broken }
+    }
+
+    fn breaks(&self) -> bool {
+        match self {
+            Doc::HardLine => true,
+            Doc::Indent(docs) => docs.iter().any(Doc::breaks),
+            Doc::Group { broken, .. } => *broken,
+            Doc::Text(_) | Doc::Space | Doc::Line | Doc::SoftLine => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+fn print(docs: &[Doc], width: usize) -> String {
+    let mut output = String::new();
+    let mut column = 0;
+    // indentation is written with the first text on a line, so blank lines stay empty
+    let mut line_start = false;
+    let mut stack: Vec<(usize, Mode, &Doc)> =
+        docs.iter().rev().map(|doc| (0, Mode::Break, doc)).collect();
+
+    while let Some((indent, mode, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(text) => {
+                if line_start && !text.is_empty() {
+                    output.push_str(&" ".repeat(column));
+                    line_start = false;
+                }
+                output.push_str(text);
+                column = match text.rfind('\n') {
+                    Some(newline) => text[newline + 1..].chars().count(),
+                    None => column + text.chars().count(),
+                };
+            }
+            Doc::Space => {
+                if !line_start && !output.ends_with([' ', '(']) {
+                    output.push(' ');
+                    column += 1;
+                }
+            }
+            Doc::Line if mode == Mode::Flat => {
+                output.push(' ');
+                column += 1;
+            }
+            Doc::SoftLine if mode == Mode::Flat => {}
+            Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                output.push('\n');
+                column = indent;
+                line_start = true;
+            }
+            Doc::Indent(docs) => {
+                stack.extend(docs.iter().rev().map(|doc| (indent + INDENT, mode, doc)));
+            }
+            Doc::Group { docs, broken } => {
+                let flat = mode == Mode::Flat
+                    || (!broken && fits(docs, &stack, width.saturating_sub(column)));
+                let mode = if flat { Mode::Flat } else { Mode::Break };
+                stack.extend(docs.iter().rev().map(|doc| (indent, mode, doc)));
+            }
+        }
+    }
+
+    output
+}
+
+// whether the docs fit on one line, together with what follows them up to the next
+// place the line may be broken
+fn fits(docs: &[Doc], rest: &[(usize, Mode, &Doc)], mut remaining: usize) -> bool {
+    let mut stack: Vec<(Mode, &Doc)> = docs.iter().rev().map(|doc| (Mode::Flat, doc)).collect();
+    let mut rest = rest.iter().rev();
+
+    loop {
+        let (mode, doc) = match stack.pop() {
+            Some(next) => next,
+            None => match rest.next() {
+                Some(&(_, mode, doc)) => (mode, doc),
+                None => return true,
+            },
+        };
+
+        let width = match doc {
+            Doc::Text(text) => text.chars().count(),
+            Doc::Space => 1,
+            Doc::Line if mode == Mode::Flat => 1,
+            Doc::SoftLine if mode == Mode::Flat => 0,
+            Doc::Line | Doc::SoftLine | Doc::HardLine => return true,
+            Doc::Indent(docs) => {
+                stack.extend(docs.iter().rev().map(|doc| (mode, doc)));
+                0
+            }
+            Doc::Group { docs, broken } => {
+                let mode = if *broken { Mode::Break } else { mode };
+                stack.extend(docs.iter().rev().map(|doc| (mode, doc)));
+                0
+            }
+        };
+
+        if width > remaining {
+            return false;
+        }
+        remaining -= width;
+    }
+}
+
+struct Comment<'a> {
+    text: &'a str,
+    // the newlines between the comment and whatever is before it
+    newlines: usize,
+    // a line comment has to be the last thing on its line
+    is_line_comment: bool,
+}
+
+// the comments before a token, and the newlines between the last of them and the token
+fn comments(token: &Token) -> (Vec<Comment<'_>>, usize) {
+    let mut comments = Vec::new();
+    let mut newlines = 0;
+
+    for trivia in token.leading_trivia.iter() {
+        match trivia.kind {
+            TriviaKind::Whitespace => newlines += trivia.text.matches('\n').count(),
+            // there is no skipped text in a source without errors
+            TriviaKind::Skipped => {}
+            kind => {
+                comments.push(Comment {
+                    text: trivia.text.trim_end(),
+                    newlines,
+                    is_line_comment: kind != TriviaKind::BlockComment,
+                });
+                newlines = 0;
+            }
+        }
+    }
+
+    (comments, newlines)
+}
+
+// Binary operators of the same precedence are laid out as one chain,
+// so that `a + b - c` is broken at every operator or at none.
+fn precedence(token_type: TokenType) -> u8 {
+    match token_type {
+        TokenType::Or => 0,
+        TokenType::And => 1,
+        TokenType::BangEqual | TokenType::EqualEqual => 2,
+        TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual => 3,
+        TokenType::Minus | TokenType::Plus => 4,
+        _ => 5,
+    }
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+    // tokens whose comments have been placed already, by where they start
+    placed: HashSet<usize>,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            placed: HashSet::new(),
+        }
+    }
+
+    fn program(&mut self, root: &SyntaxNode) -> Vec<Doc> {
+        let declarations: Vec<&SyntaxNode> = root.nodes().collect();
+        let Some(eof) = root.last_token() else {
+            return Vec::new();
+        };
+
+        let mut docs = self.lines(&declarations, eof, true);
+        if !docs.is_empty() {
+            docs.push(Doc::HardLine);
+        }
+        docs
+    }
+
+    // Declarations, statements and methods go one per line, keeping at most one blank
+    // line between them. Comments stay at the end of the line they were on, or get a
+    // line of their own. The closer is the token after the last item.
+    fn lines(&mut self, items: &[&SyntaxNode], closer: &Token, mut at_start: bool) -> Vec<Doc> {
+        let mut docs = Vec::new();
+        let mut first_line = true;
+
+        let mut next = Vec::new();
+        for item in items {
+            if let Some(token) = item.first_token() {
+                next.push((token, Some(*item)));
+            }
+        }
+        next.push((closer, None));
+
+        for (token, item) in next {
+            self.placed.insert(token.span.start);
+            let (comments, newlines) = comments(token);
+            let mut comments = comments.iter().peekable();
+
+            if !at_start {
+                while let Some(comment) = comments.next_if(|comment| comment.newlines == 0) {
+                    docs.push(Doc::text(" "));
+                    docs.push(Doc::text(comment.text));
+                }
+            }
+            for comment in comments {
+                Self::new_line(&mut docs, comment.newlines, &mut first_line, at_start);
+                docs.push(Doc::text(comment.text));
+                at_start = false;
+            }
+
+            if let Some(item) = item {
+                Self::new_line(&mut docs, newlines, &mut first_line, at_start);
+                docs.extend(self.node(item));
+                at_start = false;
+            }
+        }
+
+        docs
+    }
+
+    fn new_line(docs: &mut Vec<Doc>, newlines: usize, first_line: &mut bool, at_start: bool) {
+        if !at_start {
+            docs.push(Doc::HardLine);
+        }
+        if newlines > 1 && !*first_line {
+            docs.push(Doc::HardLine);
+        }
+        *first_line = false;
+    }
+
+    fn node(&mut self, node: &SyntaxNode) -> Vec<Doc> {
+        match node.kind {
+            SyntaxKind::Program => self.program(node),
+            SyntaxKind::ClassDeclaration => self.class_declaration(node),
+            SyntaxKind::FunctionDeclaration | SyntaxKind::Method => self.function(node),
+            SyntaxKind::ParameterList | SyntaxKind::ArgumentList => self.arguments(node),
+            SyntaxKind::Block => self.block(node),
+            SyntaxKind::ForStatement => self.for_statement(node),
+            SyntaxKind::IfStatement => self.if_statement(node),
+            SyntaxKind::WhileStatement => self.while_statement(node),
+            SyntaxKind::Binary | SyntaxKind::Logical => self.binary(node),
+            SyntaxKind::Interpolation => self.interpolation(node),
+            SyntaxKind::Unary => self.unary(node),
+            SyntaxKind::VarDeclaration
+            | SyntaxKind::ExpressionStatement
+            | SyntaxKind::PrintStatement
+            | SyntaxKind::ReturnStatement
+            | SyntaxKind::Assignment
+            | SyntaxKind::Error => self.spaced(&node.children),
+            SyntaxKind::Call
+            | SyntaxKind::Get
+            | SyntaxKind::Grouping
+            | SyntaxKind::Literal
+            | SyntaxKind::Variable
+            | SyntaxKind::This
+            | SyntaxKind::Super => self.joined(&node.children),
+        }
+    }
+
+    fn element(&mut self, element: &SyntaxElement) -> Vec<Doc> {
+        match element {
+            SyntaxElement::Node(node) => self.node(node),
+            SyntaxElement::Token(token) => self.token(token),
+        }
+    }
+
+    // an element starting a line of a broken list or chain, which is indented already,
+    // so what follows a line comment before it lines up with the comment
+    fn line_element(&mut self, element: &SyntaxElement) -> Vec<Doc> {
+        let first = match element {
+            SyntaxElement::Node(node) => node.first_token(),
+            SyntaxElement::Token(token) => Some(token),
+        };
+        let mut docs = first.map_or_else(Vec::new, |token| self.comments(token, || Doc::HardLine));
+        docs.extend(self.element(element));
+        docs
+    }
+
+    // the token as written, after any comments before it that haven't been placed,
+    // a token after a line comment continues its statement on an indented line
+    fn token(&mut self, token: &Token) -> Vec<Doc> {
+        let mut docs = self.comments(token, || Doc::Indent(vec![Doc::HardLine]));
+        docs.push(Doc::text(&self.source[token.span.start..token.span.end]));
+        docs
+    }
+
+    // the comments before a token that haven't been placed, with the given line break
+    // after each line comment
+    fn comments(&mut self, token: &Token, line_break: impl Fn() -> Doc) -> Vec<Doc> {
+        let mut docs = Vec::new();
+        if self.placed.insert(token.span.start) {
+            // punctuation closing a list or statement goes right after a block comment
+            let closes = matches!(
+                token.token_type,
+                TokenType::Comma | TokenType::RightParen | TokenType::Semicolon
+            );
+            for comment in comments(token).0 {
+                docs.push(Doc::Space);
+                docs.push(Doc::text(comment.text));
+                if comment.is_line_comment {
+                    docs.push(line_break());
+                } else if !closes {
+                    docs.push(Doc::Space);
+                }
+            }
+        }
+        docs
+    }
+
+    fn joined(&mut self, elements: &[SyntaxElement]) -> Vec<Doc> {
+        elements
+            .iter()
+            .flat_map(|element| self.element(element))
+            .collect()
+    }
+
+    // separated by single spaces, but for the semicolon ending a statement
+    fn spaced(&mut self, elements: &[SyntaxElement]) -> Vec<Doc> {
+        let mut docs = Vec::new();
+        for (index, element) in elements.iter().enumerate() {
+            let semicolon = matches!(
+                element,
+                SyntaxElement::Token(token) if token.token_type == TokenType::Semicolon
+            );
+            if index > 0 && !semicolon {
+                docs.push(Doc::text(" "));
+            }
+            docs.extend(self.element(element));
+        }
+        docs
+    }
+
+    fn class_declaration(&mut self, node: &SyntaxNode) -> Vec<Doc> {
+        let body = node
+            .children
+            .iter()
+            .position(|child| {
+                matches!(child, SyntaxElement::Token(token) if token.token_type == TokenType::LeftBrace)
+            })
+            .unwrap_or(node.children.len());
+
+        let mut docs = self.spaced(&node.children[..body]);
+        docs.push(Doc::text(" "));
+
+        let methods: Vec<&SyntaxNode> = node
+            .nodes()
+            .filter(|child| child.kind == SyntaxKind::Method)
+            .collect();
+        docs.extend(self.braces(&node.children[body..], &methods));
+        docs
+    }
+
+    fn function(&mut self, node: &SyntaxNode) -> Vec<Doc> {
+        let mut docs = Vec::new();
+        for (index, child) in node.children.iter().enumerate() {
+            match child {
+                SyntaxElement::Node(parameters) if parameters.kind == SyntaxKind::ParameterList => {
+                    docs.extend(self.arguments(parameters));
+                }
+                child => {
+                    if index > 0 {
+                        docs.push(Doc::text(" "));
+                    }
+                    docs.extend(self.element(child));
+                }
+            }
+        }
+        docs
+    }
+
+    // `(a, b)` on one line, or one per line when they don't fit
+    fn arguments(&mut self, node: &SyntaxNode) -> Vec<Doc> {
+        let [open, arguments @ .., close] = node.children.as_slice() else {
+            return self.joined(&node.children);
+        };
+
+        let mut docs = self.element(open);
+        let mut indented = Vec::new();
+        if !arguments.is_empty() {
+            indented.push(Doc::SoftLine);
+            for argument in arguments {
+                if matches!(argument, SyntaxElement::Token(token) if token.token_type == TokenType::Comma)
+                {
+                    indented.extend(self.element(argument));
+                    indented.push(Doc::Line);
+                } else {
+                    indented.extend(self.line_element(argument));
+                }
+            }
+        }
+
+        // comments before the closing paren stay after the last argument, and the
+        // paren goes on a line of its own after a line comment
+        let mut after_line_comment = false;
+        if let SyntaxElement::Token(close) = close {
+            indented.extend(self.comments(close, || Doc::HardLine));
+            after_line_comment = indented.last().is_some_and(Doc::breaks);
+            if after_line_comment {
+                indented.pop();
+            }
+        }
+        if !indented.is_empty() {
+            docs.push(Doc::Indent(indented));
+        }
+        if after_line_comment {
+            docs.push(Doc::HardLine);
+        } else if !arguments.is_empty() {
+            docs.push(Doc::SoftLine);
+        }
+        docs.extend(self.element(close));
+
+        vec![Doc::group(docs)]
+    }
+
+    fn block(&mut self, node: &SyntaxNode) -> Vec<Doc> {
+        let statements: Vec<&SyntaxNode> = node.nodes().collect();
+        self.braces(&node.children, &statements)
+    }
+
+    // the elements from the opening to the closing brace, with the items between them
+    fn braces(&mut self, elements: &[SyntaxElement], items: &[&SyntaxNode]) -> Vec<Doc> {
+        let (Some(SyntaxElement::Token(open)), Some(SyntaxElement::Token(close))) =
+            (elements.first(), elements.last())
+        else {
+            return self.joined(elements);
+        };
+
+        let mut docs = self.token(open);
+        let lines = self.lines(items, close, false);
+        if !lines.is_empty() {
+            docs.push(Doc::Indent(lines));
+            docs.push(Doc::HardLine);
+        }
+        docs.extend(self.token(close));
+        docs
+    }
+
+    // the keyword and the parenthesized condition of an if or a while
+    fn condition(&mut self, elements: &[SyntaxElement]) -> Vec<Doc> {
+        let mut docs = Vec::new();
+        for (index, element) in elements.iter().enumerate() {
+            if index == 1 {
+                docs.push(Doc::text(" "));
+            }
+            docs.extend(self.element(element));
+        }
+        docs
+    }
+
+    // a block stays on the line, any other statement moves to the next one if needed
+    fn body(&mut self, element: &SyntaxElement) -> Vec<Doc> {
+        let mut docs = self.element(element);
+        if Self::is_block(element) {
+            docs.insert(0, Doc::text(" "));
+            docs
+        } else {
+            docs.insert(0, Doc::Line);
+            vec![Doc::group(vec![Doc::Indent(docs)])]
+        }
+    }
+
+    fn is_block(element: &SyntaxElement) -> bool {
+        matches!(element, SyntaxElement::Node(node) if node.kind == SyntaxKind::Block)
+    }
+
+    fn if_statement(&mut self, node: &SyntaxNode) -> Vec<Doc> {
+        let children = &node.children;
+        let mut docs = self.condition(&children[..4]);
+        docs.extend(self.body(&children[4]));
+
+        if let [SyntaxElement::Token(keyword), else_branch] = &children[5..] {
+            // comments on the line of the then branch stay there, the others go on
+            // lines of their own before the `else`
+            self.placed.insert(keyword.span.start);
+            let mut on_line = true;
+            for comment in comments(keyword).0 {
+                on_line = on_line && comment.newlines == 0;
+                docs.push(if on_line {
+                    Doc::text(" ")
+                } else {
+                    Doc::HardLine
+                });
+                docs.push(Doc::text(comment.text));
+                on_line = on_line && !comment.is_line_comment;
+            }
+
+            // `else` goes after the closing brace, or on a line of its own
+            docs.push(if on_line && Self::is_block(&children[4]) {
+                Doc::text(" ")
+            } else {
+                Doc::HardLine
+            });
+            docs.extend(self.token(keyword));
+
+            if matches!(else_branch, SyntaxElement::Node(node) if node.kind == SyntaxKind::IfStatement)
+            {
+                docs.push(Doc::text(" "));
+                docs.extend(self.element(else_branch));
+            } else {
+                docs.extend(self.body(else_branch));
+            }
+        }
+        docs
+    }
+
+    fn while_statement(&mut self, node: &SyntaxNode) -> Vec<Doc> {
+        let mut docs = self.condition(&node.children[..4]);
+        docs.extend(self.body(&node.children[4]));
+        docs
+    }
+
+    // `for (initializer; condition; increment)`, where the initializer is a
+    // declaration or statement with its own semicolon, or a lone semicolon
+    fn for_statement(&mut self, node: &SyntaxNode) -> Vec<Doc> {
+        let children = &node.children;
+        let mut docs = self.condition(&children[..3]);
+
+        let mut index = 3;
+        for _ in 0..2 {
+            if let SyntaxElement::Node(clause) = &children[index] {
+                docs.push(Doc::text(" "));
+                docs.extend(self.node(clause));
+                index += 1;
+            }
+            // the semicolon after the condition, then the closing paren
+            docs.extend(self.element(&children[index]));
+            index += 1;
+        }
+
+        docs.extend(self.body(&children[index]));
+        docs
+    }
+
+    // `- -a` keeps its space, as `--a` reads like a decrement
+    fn unary(&mut self, node: &SyntaxNode) -> Vec<Doc> {
+        let [SyntaxElement::Token(operator), SyntaxElement::Node(right)] = node.children.as_slice()
+        else {
+            return self.joined(&node.children);
+        };
+
+        let mut docs = self.token(operator);
+        if operator.token_type == TokenType::Minus
+            && right
+                .first_token()
+                .is_some_and(|token| token.token_type == TokenType::Minus)
+        {
+            docs.push(Doc::text(" "));
+        }
+        docs.extend(self.node(right));
+        docs
+    }
+
+    // `a + b - c` as one chain, with the operators ending the lines when it is broken
+    fn binary(&mut self, node: &SyntaxNode) -> Vec<Doc> {
+        let mut operands = Vec::new();
+        Self::chain(node, &mut operands);
+
+        let (first, rest) = operands.split_at(1);
+        let mut docs = self.element(first[0].1);
+        let mut indented = Vec::new();
+        for (operator, operand) in rest {
+            indented.push(Doc::text(" "));
+            // comments before an operator move after it, to stay out of its way
+            let mut comments = Vec::new();
+            if let Some(operator) = operator {
+                comments = self.comments(operator, || Doc::HardLine);
+                indented.extend(self.token(operator));
+            }
+            indented.push(Doc::Line);
+            indented.extend(comments);
+            indented.extend(self.line_element(operand));
+        }
+        docs.push(Doc::Indent(indented));
+
+        vec![Doc::group(docs)]
+    }
+
+    // the operands of a chain and the operator before each of them
+    fn chain<'n>(node: &'n SyntaxNode, operands: &mut Vec<(Option<&'n Token>, &'n SyntaxElement)>) {
+        let [left, SyntaxElement::Token(operator), right] = node.children.as_slice() else {
+            operands.extend(node.children.iter().map(|child| (None, child)));
+            return;
+        };
+
+        match left {
+            SyntaxElement::Node(left_node)
+                if left_node.kind == node.kind
+                    && matches!(
+                        left_node.children.get(1),
+                        Some(SyntaxElement::Token(left_operator))
+                            if precedence(left_operator.token_type) == precedence(operator.token_type)
+                    ) =>
+            {
+                Self::chain(left_node, operands);
+            }
+            _ => operands.push((None, left)),
+        }
+        operands.push((Some(operator), right));
+    }
+
+    // the string segments as written, with each expression between them on one line
+    fn interpolation(&mut self, node: &SyntaxNode) -> Vec<Doc> {
+        let mut docs = Vec::new();
+        for child in &node.children {
+            match child {
+                SyntaxElement::Node(expression) => {
+                    let expression = self.node(expression);
+                    docs.push(Doc::Text(print(&expression, usize::MAX)));
+                }
+                SyntaxElement::Token(segment) => docs.extend(self.token(segment)),
+            }
+        }
+        docs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+    use crate::scanner::Lexer;
+
+    fn format_with(source: &str, width: usize) -> String {
+        format(source, &FormatOptions { width }).expect("expected no errors")
+    }
+
+    // formatting again changes nothing, and no token was added, lost or reordered
+    fn assert_stable(source: &str, formatted: &str, width: usize) {
+        assert_eq!(format_with(formatted, width), formatted, "not idempotent");
+
+        let tokens = |source: &str| -> Vec<(TokenType, String)> {
+            Lexer::new(source)
+                .map(Result::unwrap)
+                .map(|token| (token.token_type, token.lexeme.to_string()))
+                .collect()
+        };
+        assert_eq!(tokens(source), tokens(formatted));
+    }
+
+    #[test]
+    fn test_formats_layout() {
+        let source = "var a=1;fun add(x,y){return x+y;}\nclass A<B{init(){this.x=-a;}\n\n\n\nget(){return super.get().x;}}\nif(a>1)print a;else{print \"${ a+1 }!\";}\nfor(var i=0;i<3;i=i+1)print i;for(;;){}\nwhile(true and !false)a=add(1,2);";
+        let formatted = format_with(source, 80);
+
+        assert_eq!(
+            formatted,
+            "\
+var a = 1;
+fun add(x, y) {
+    return x + y;
+}
+class A < B {
+    init() {
+        this.x = -a;
+    }
+
+    get() {
+        return super.get().x;
+    }
+}
+if (a > 1) print a;
+else {
+    print \"${a + 1}!\";
+}
+for (var i = 0; i < 3; i = i + 1) print i;
+for (;;) {}
+while (true and !false) a = add(1, 2);
+"
+        );
+        assert_stable(source, &formatted, 80);
+        assert_eq!(format_with("print - -a;", 80), "print - -a;\n");
+    }
+
+    #[test]
+    fn test_keeps_comments() {
+        let source = "  // header\n\n\n/// Documented.\nvar a = 1;   // trailing\n{ // after brace\n  print a; /* inline */\n\n  // before closing\n}\nprint /* before */ a;\nfun f(a /* x */, b /* y */) { return a /* z */; }\nprint a // why\n  + 1;\nif (a) print 1; // c\nelse print 2;\nif (a) {} // d\n// e\nelse {}\n// at the end\n";
+        let formatted = format_with(source, 80);
+
+        assert_eq!(
+            formatted,
+            "\
+// header
+
+/// Documented.
+var a = 1; // trailing
+{ // after brace
+    print a; /* inline */
+
+    // before closing
+}
+print /* before */ a;
+fun f(a /* x */, b /* y */) {
+    return a /* z */;
+}
+print a +
+    // why
+    1;
+if (a) print 1; // c
+else print 2;
+if (a) {} // d
+// e
+else {}
+// at the end
+"
+        );
+        assert_stable(source, &formatted, 80);
+    }
+
+    #[test]
+    fn test_comments_next_to_punctuation() {
+        let source = "fun f(/* none */) {}\nprint a // c\n;\n{ print a // c\n; }\nf(1 // x\n);";
+        let formatted = format_with(source, 80);
+
+        assert_eq!(
+            formatted,
+            "\
+fun f(/* none */) {}
+print a // c
+    ;
+{
+    print a // c
+        ;
+}
+f(
+    1 // x
+);
+"
+        );
+        assert_stable(source, &formatted, 80);
+    }
+
+    #[test]
+    fn test_wraps_long_expressions() {
+        let source = "print first + second * third - fourth;\nresult = compute(alpha, beta, gamma and delta);";
+        let formatted = format_with(source, 24);
+
+        assert_eq!(
+            formatted,
+            "\
+print first +
+    second * third -
+    fourth;
+result = compute(
+    alpha,
+    beta,
+    gamma and delta
+);
+"
+        );
+        assert_eq!(format_with(&formatted, 24), formatted);
+        assert_eq!(
+            format_with(&formatted, 80),
+            "print first + second * third - fourth;\nresult = compute(alpha, beta, gamma and delta);\n"
+        );
+    }
+
+    #[test]
+    fn test_nested_statements_are_stable() {
+        let sources = [
+            "if (a) if (b) print 1; else print 2;",
+            "if (a) { print 1; } else if (b) { print 2; } else print 3;",
+            "fun f() { for (var i = 0; i < 10; i = i + 1) { while (i) { i = i - 1; } } }",
+            "",
+            "// only a comment",
+            "var a = (1 + 2) * 3 / (4 - -5) == 6 or 7 < 8 and nil != \"x\";",
+            "print - -a; print -(-a); print !!a;",
+            "if (a) print 1; // c\nelse print 2;",
+            "if (a) { print 1; } /* c */ else /* d */ print 2;",
+        ];
+
+        for source in sources {
+            let formatted = format_with(source, 40);
+            assert_stable(source, &formatted, 40);
+        }
+    }
+
+    #[test]
+    fn test_refuses_sources_with_errors() {
+        let errors = format("var a = ;", &FormatOptions::default()).unwrap_err();
+        assert_eq!(
+            errors[0].to_string(),
+            "[line 1] Error at ';': Expect expression."
+        );
+
+        let errors = format("print \"open;", &FormatOptions::default()).unwrap_err();
+        assert_eq!(errors[0].code(), ErrorCode::UnterminatedString);
+    }
+}
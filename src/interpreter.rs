@@ -0,0 +1,1021 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::class::{LoxClass, LoxInstance};
+
+use crate::domain::expression::ExpressionId;
+use crate::domain::statement::FunctionDeclaration;
+use crate::domain::{token::Token, Expression, Statement, TokenType, Value};
+use crate::environment::Environment;
+use crate::error::{ErrorCode, LoxError};
+use crate::function::{Callable, LoxFunction, NativeFunction, MAX_CALL_DEPTH};
+use crate::parser::Parser;
+use crate::resolver::Resolver;
+use crate::scanner::Lexer;
+
+/*
+    The Interpreter walks the syntax tree produced by the Parser and evaluates it.
+
+    Reference - https://craftinginterpreters.com/evaluating-expressions.html
+*/
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{message}\n[line {}]", .token.line)]
+pub struct RuntimeError {
+    pub code: ErrorCode,
+    pub message: String,
+    // boxed so that every result that may hold the error stays small
+    pub token: Box<Token>,
+}
+
+impl RuntimeError {
+    pub fn new(code: ErrorCode, message: String, token: Token) -> Self {
+        Self {
+            code,
+            message,
+            token: Box::new(token),
+        }
+    }
+}
+
+// Executing a statement stops early either because of a runtime error
+// or because a return statement is unwinding to the enclosing call.
+#[derive(Debug)]
+pub(crate) enum Unwind {
+    Error(RuntimeError),
+    Return(Value),
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(error: RuntimeError) -> Self {
+        Self::Error(error)
+    }
+}
+
+// The native stack one Lox call takes in a debug build, about 6 KB for a call made
+// from a return statement and about 17 KB from inside nested loops and blocks, with
+// room to spare. Release builds take about a quarter of that.
+const CALL_STACK_SIZE: usize = 32 * 1024;
+
+// The stack size of the thread the interpreter runs on, deep enough for MAX_CALL_DEPTH
+// calls on top of the frames of the statement that makes the first one.
+pub const STACK_SIZE: usize = (MAX_CALL_DEPTH + 16) * CALL_STACK_SIZE;
+
+#[derive(Debug)]
+pub struct Interpreter {
+    globals: Rc<RefCell<Environment>>,
+    environment: Rc<RefCell<Environment>>,
+    // scope distances of local variables, filled in by the Resolver
+    locals: HashMap<ExpressionId, usize>,
+    // how many calls are being made, see `evaluate_call`
+    pub(crate) call_depth: usize,
+    pub(crate) max_call_depth: usize,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let mut globals = Environment::new();
+        globals.define(
+            "clock".into(),
+            Value::NativeFunction(Rc::new(NativeFunction::clock())),
+        );
+
+        let globals = Rc::new(RefCell::new(globals));
+
+        Self {
+            environment: Rc::clone(&globals),
+            globals,
+            locals: HashMap::new(),
+            call_depth: 0,
+            max_call_depth: MAX_CALL_DEPTH,
+        }
+    }
+
+    pub(crate) fn resolve(&mut self, id: ExpressionId, depth: usize) {
+        self.locals.insert(id, depth);
+    }
+
+    pub fn interpret(&mut self, statements: &[Statement]) -> Result<(), RuntimeError> {
+        for statement in statements {
+            match self.execute(statement) {
+                Ok(()) => {}
+                // a return outside of any function ends the program
+                Err(Unwind::Return(_)) => return Ok(()),
+                Err(Unwind::Error(error)) => return Err(error),
+            }
+        }
+        Ok(())
+    }
+
+    // scans, parses, resolves and interprets source, keeping this interpreter's state,
+    // errors from the first stage that fails are returned together
+    pub fn run(&mut self, source: &str) -> Result<(), Vec<LoxError>> {
+        // the parser pulls tokens straight from the lexer, scanning errors are set
+        // aside and take precedence over the syntax errors they cause
+        let mut scanner_errors = Vec::new();
+        let tokens = Lexer::new(source).filter_map(|scanned| {
+            scanned
+                .map_err(|error| scanner_errors.push(LoxError::from(error)))
+                .ok()
+        });
+        let parsed = Parser::new(tokens).parse();
+        if !scanner_errors.is_empty() {
+            return Err(scanner_errors);
+        }
+
+        let program =
+            parsed.map_err(|errors| errors.into_iter().map(LoxError::from).collect::<Vec<_>>())?;
+
+        let mut resolver = Resolver::new(self);
+        resolver.resolve(&program.statements);
+        if !resolver.errors.is_empty() {
+            return Err(resolver.errors.into_iter().map(LoxError::from).collect());
+        }
+
+        self.interpret(&program.statements)
+            .map_err(|error| vec![LoxError::from(error)])
+    }
+
+    // `execute` and `evaluate` recurse for every nested statement and expression, so
+    // they only dispatch, the locals of each kind of node live in its own method and
+    // keep the native stack a Lox call takes small
+    fn execute(&mut self, statement: &Statement) -> Result<(), Unwind> {
+        match statement {
+            Statement::Expression(expression) => {
+                self.evaluate(expression)?;
+                Ok(())
+            }
+            Statement::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => self.execute_if(condition, then_branch, else_branch.as_deref()),
+            Statement::While { condition, body } => self.execute_while(condition, body),
+            Statement::Print(expression) => self.execute_print(expression),
+            Statement::Var {
+                name, initializer, ..
+            } => self.execute_var(name, initializer.as_ref()),
+            Statement::Class {
+                name,
+                superclass,
+                methods,
+                ..
+            } => self.execute_class(name, superclass.as_ref(), methods),
+            Statement::Function(declaration) => {
+                self.execute_function(declaration);
+                Ok(())
+            }
+            Statement::Return { value, .. } => self.execute_return(value.as_ref()),
+            Statement::Block(statements) => {
+                let environment = Environment::new_enclosed(Rc::clone(&self.environment));
+                self.execute_block(statements, Rc::new(RefCell::new(environment)))
+            }
+        }
+    }
+
+    fn execute_if(
+        &mut self,
+        condition: &Expression,
+        then_branch: &Statement,
+        else_branch: Option<&Statement>,
+    ) -> Result<(), Unwind> {
+        if self.evaluate(condition)?.is_truthy() {
+            self.execute(then_branch)
+        } else if let Some(else_branch) = else_branch {
+            self.execute(else_branch)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn execute_while(&mut self, condition: &Expression, body: &Statement) -> Result<(), Unwind> {
+        while self.evaluate(condition)?.is_truthy() {
+            self.execute(body)?;
+        }
+        Ok(())
+    }
+
+    fn execute_print(&mut self, expression: &Expression) -> Result<(), Unwind> {
+        let value = self.evaluate(expression)?;
+        println!("{}", value);
+        Ok(())
+    }
+
+    fn execute_var(
+        &mut self,
+        name: &Token,
+        initializer: Option<&Expression>,
+    ) -> Result<(), Unwind> {
+        let value = match initializer {
+            Some(initializer) => self.evaluate(initializer)?,
+            None => Value::Nil,
+        };
+        self.environment
+            .borrow_mut()
+            .define(name.symbol.clone(), value);
+        Ok(())
+    }
+
+    fn execute_function(&mut self, declaration: &Rc<FunctionDeclaration>) {
+        let function =
+            LoxFunction::new(Rc::clone(declaration), Rc::clone(&self.environment), false);
+        self.environment.borrow_mut().define(
+            declaration.name.symbol.clone(),
+            Value::Function(Rc::new(function)),
+        );
+    }
+
+    fn execute_return(&mut self, value: Option<&Expression>) -> Result<(), Unwind> {
+        let value = match value {
+            Some(value) => self.evaluate(value)?,
+            None => Value::Nil,
+        };
+        Err(Unwind::Return(value))
+    }
+
+    fn execute_class(
+        &mut self,
+        name: &Token,
+        superclass: Option<&Expression>,
+        methods: &[Rc<FunctionDeclaration>],
+    ) -> Result<(), Unwind> {
+        let superclass = match superclass {
+            Some(superclass) => match self.evaluate(superclass)? {
+                Value::Class(class) => Some(class),
+                _ => {
+                    let token = match superclass {
+                        Expression::Variable { name, .. } => name.clone(),
+                        _ => name.clone(),
+                    };
+                    return Err(Unwind::Error(RuntimeError::new(
+                        ErrorCode::SuperclassNotAClass,
+                        "Superclass must be a class.".to_string(),
+                        token,
+                    )));
+                }
+            },
+            None => None,
+        };
+
+        self.environment
+            .borrow_mut()
+            .define(name.symbol.clone(), Value::Nil);
+
+        // methods of a subclass close over an extra scope binding "super"
+        let mut closure = Rc::clone(&self.environment);
+        if let Some(superclass) = &superclass {
+            let mut environment = Environment::new_enclosed(closure);
+            environment.define("super".into(), Value::Class(Rc::clone(superclass)));
+            closure = Rc::new(RefCell::new(environment));
+        }
+
+        let mut class_methods = HashMap::new();
+        for method in methods {
+            let function = LoxFunction::new(
+                Rc::clone(method),
+                Rc::clone(&closure),
+                &*method.name.symbol == "init",
+            );
+            class_methods.insert(method.name.symbol.clone(), Rc::new(function));
+        }
+
+        let class = LoxClass::new(name.lexeme.to_string(), superclass, class_methods);
+        self.environment
+            .borrow_mut()
+            .assign(name, Value::Class(Rc::new(class)))?;
+        Ok(())
+    }
+
+    // executes the statements in the given environment,
+    // restoring the previous one even if a statement fails
+    pub(crate) fn execute_block(
+        &mut self,
+        statements: &[Statement],
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<(), Unwind> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+
+        let result = statements
+            .iter()
+            .try_for_each(|statement| self.execute(statement));
+
+        self.environment = previous;
+        result
+    }
+
+    pub fn evaluate(&mut self, expression: &Expression) -> Result<Value, RuntimeError> {
+        match expression {
+            Expression::Literal { value, .. } => Ok(Value::from(value.clone())),
+            Expression::Grouping { expression, .. } => self.evaluate(expression),
+            Expression::Interpolation { parts, .. } => self.evaluate_interpolation(parts),
+            Expression::Call {
+                callee,
+                paren,
+                arguments,
+                ..
+            } => self.evaluate_call(callee, paren, arguments),
+            Expression::Unary {
+                operator, right, ..
+            } => self.evaluate_unary(operator, right),
+            Expression::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } => self.evaluate_binary(left, operator, right),
+            Expression::Logical {
+                left,
+                operator,
+                right,
+                ..
+            } => self.evaluate_logical(left, operator, right),
+            Expression::Get { object, name, .. } => self.evaluate_get(object, name),
+            Expression::Set {
+                object,
+                name,
+                value,
+                ..
+            } => self.evaluate_set(object, name, value),
+            Expression::This { id, keyword, .. } => self.look_up_variable(*id, keyword),
+            Expression::Super {
+                id,
+                keyword,
+                method,
+                ..
+            } => self.evaluate_super(*id, keyword, method),
+            Expression::Variable { id, name, .. } => self.look_up_variable(*id, name),
+            Expression::Assign {
+                id, name, value, ..
+            } => self.evaluate_assign(*id, name, value),
+        }
+    }
+
+    fn evaluate_call(
+        &mut self,
+        callee: &Expression,
+        paren: &Token,
+        arguments: &[Expression],
+    ) -> Result<Value, RuntimeError> {
+        let callee = self.evaluate(callee)?;
+
+        let mut values = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            values.push(self.evaluate(argument)?);
+        }
+
+        let function: &dyn Callable = match &callee {
+            Value::Function(function) => function.as_ref(),
+            Value::NativeFunction(function) => function.as_ref(),
+            Value::Class(class) => class,
+            _ => {
+                return Err(RuntimeError::new(
+                    ErrorCode::NotCallable,
+                    "Can only call functions and classes.".to_string(),
+                    paren.clone(),
+                ))
+            }
+        };
+
+        if values.len() != function.arity() {
+            return Err(RuntimeError::new(
+                ErrorCode::ArityMismatch,
+                format!(
+                    "Expected {} arguments but got {}.",
+                    function.arity(),
+                    values.len()
+                ),
+                paren.clone(),
+            ));
+        }
+
+        // calls recurse on the native stack, so their depth is limited, see MAX_CALL_DEPTH
+        if self.call_depth >= self.max_call_depth {
+            return Err(RuntimeError::new(
+                ErrorCode::StackOverflow,
+                "Stack overflow.".to_string(),
+                paren.clone(),
+            ));
+        }
+
+        self.call_depth += 1;
+        let result = function.call(self, values);
+        self.call_depth -= 1;
+        result
+    }
+
+    // every part is stringified the way print would show it
+    fn evaluate_interpolation(&mut self, parts: &[Expression]) -> Result<Value, RuntimeError> {
+        let mut string = String::new();
+        for part in parts {
+            let value = self.evaluate(part)?;
+            string.push_str(&value.to_string());
+        }
+        Ok(Value::String(string))
+    }
+
+    fn evaluate_logical(
+        &mut self,
+        left: &Expression,
+        operator: &Token,
+        right: &Expression,
+    ) -> Result<Value, RuntimeError> {
+        let left = self.evaluate(left)?;
+
+        // short-circuit, returning the operand itself rather than a boolean
+        if operator.token_type == TokenType::Or {
+            if left.is_truthy() {
+                return Ok(left);
+            }
+        } else if !left.is_truthy() {
+            return Ok(left);
+        }
+
+        self.evaluate(right)
+    }
+
+    fn evaluate_get(&mut self, object: &Expression, name: &Token) -> Result<Value, RuntimeError> {
+        match self.evaluate(object)? {
+            Value::Instance(instance) => LoxInstance::get(&instance, name),
+            _ => Err(RuntimeError::new(
+                ErrorCode::NotAnInstance,
+                "Only instances have properties.".to_string(),
+                name.clone(),
+            )),
+        }
+    }
+
+    fn evaluate_set(
+        &mut self,
+        object: &Expression,
+        name: &Token,
+        value: &Expression,
+    ) -> Result<Value, RuntimeError> {
+        let Value::Instance(instance) = self.evaluate(object)? else {
+            return Err(RuntimeError::new(
+                ErrorCode::NotAnInstance,
+                "Only instances have fields.".to_string(),
+                name.clone(),
+            ));
+        };
+
+        let value = self.evaluate(value)?;
+        instance.borrow_mut().set(name, value.clone());
+        Ok(value)
+    }
+
+    fn evaluate_super(
+        &self,
+        id: ExpressionId,
+        keyword: &Token,
+        method: &Token,
+    ) -> Result<Value, RuntimeError> {
+        // "this" is always bound in the scope just inside the one binding "super"
+        let distance = self.locals.get(&id).copied().unwrap_or_default();
+        let superclass = self.environment.borrow().get_at(distance, "super");
+        let instance = match distance.checked_sub(1) {
+            Some(distance) => self.environment.borrow().get_at(distance, "this"),
+            None => None,
+        };
+
+        let (Some(Value::Class(superclass)), Some(Value::Instance(instance))) =
+            (superclass, instance)
+        else {
+            return Err(RuntimeError::new(
+                ErrorCode::SuperOutsideClass,
+                "Can't use 'super' outside of a class.".to_string(),
+                keyword.clone(),
+            ));
+        };
+
+        match superclass.find_method(&method.symbol) {
+            Some(function) => Ok(Value::Function(Rc::new(function.bind(instance)))),
+            None => Err(RuntimeError::new(
+                ErrorCode::UndefinedProperty,
+                format!("Undefined property '{}'.", method.lexeme),
+                method.clone(),
+            )),
+        }
+    }
+
+    fn evaluate_assign(
+        &mut self,
+        id: ExpressionId,
+        name: &Token,
+        value: &Expression,
+    ) -> Result<Value, RuntimeError> {
+        let value = self.evaluate(value)?;
+
+        match self.locals.get(&id) {
+            Some(distance) => {
+                self.environment
+                    .borrow_mut()
+                    .assign_at(*distance, name, value.clone())?;
+            }
+            None => {
+                self.globals.borrow_mut().assign(name, value.clone())?;
+            }
+        }
+
+        Ok(value)
+    }
+
+    // unresolved variables are assumed to be global
+    fn look_up_variable(&self, id: ExpressionId, name: &Token) -> Result<Value, RuntimeError> {
+        match self.locals.get(&id) {
+            Some(distance) => self
+                .environment
+                .borrow()
+                .get_at(*distance, &name.symbol)
+                .ok_or_else(|| {
+                    RuntimeError::new(
+                        ErrorCode::UndefinedVariable,
+                        format!("Undefined variable '{}'.", name.lexeme),
+                        name.clone(),
+                    )
+                }),
+            None => self.globals.borrow().get(name),
+        }
+    }
+
+    fn evaluate_unary(
+        &mut self,
+        operator: &Token,
+        right: &Expression,
+    ) -> Result<Value, RuntimeError> {
+        let right = self.evaluate(right)?;
+        Self::unary_operation(operator, right)
+    }
+
+    fn unary_operation(operator: &Token, right: Value) -> Result<Value, RuntimeError> {
+        match operator.token_type {
+            TokenType::Minus => {
+                let number = Self::number_operand(operator, &right)?;
+                Ok(Value::Number(-number))
+            }
+            TokenType::Bang => Ok(Value::Boolean(!right.is_truthy())),
+            _ => Err(RuntimeError::new(
+                ErrorCode::UnknownOperator,
+                format!("Unknown unary operator '{}'.", operator.lexeme),
+                operator.clone(),
+            )),
+        }
+    }
+
+    fn evaluate_binary(
+        &mut self,
+        left: &Expression,
+        operator: &Token,
+        right: &Expression,
+    ) -> Result<Value, RuntimeError> {
+        let left = self.evaluate(left)?;
+        let right = self.evaluate(right)?;
+        Self::binary_operation(left, operator, right)
+    }
+
+    fn binary_operation(
+        left: Value,
+        operator: &Token,
+        right: Value,
+    ) -> Result<Value, RuntimeError> {
+        match operator.token_type {
+            TokenType::EqualEqual => Ok(Value::Boolean(left == right)),
+            TokenType::BangEqual => Ok(Value::Boolean(left != right)),
+            TokenType::Plus => match (left, right) {
+                (Value::Number(left), Value::Number(right)) => Ok(Value::Number(left + right)),
+                (Value::String(left), Value::String(right)) => Ok(Value::String(left + &right)),
+                _ => Err(RuntimeError::new(
+                    ErrorCode::OperandType,
+                    "Operands must be two numbers or two strings.".to_string(),
+                    operator.clone(),
+                )),
+            },
+            _ => {
+                let (left, right) = Self::number_operands(operator, &left, &right)?;
+                match operator.token_type {
+                    TokenType::Minus => Ok(Value::Number(left - right)),
+                    TokenType::Star => Ok(Value::Number(left * right)),
+                    TokenType::Slash => Ok(Value::Number(left / right)),
+                    TokenType::Greater => Ok(Value::Boolean(left > right)),
+                    TokenType::GreaterEqual => Ok(Value::Boolean(left >= right)),
+                    TokenType::Less => Ok(Value::Boolean(left < right)),
+                    TokenType::LessEqual => Ok(Value::Boolean(left <= right)),
+                    _ => Err(RuntimeError::new(
+                        ErrorCode::UnknownOperator,
+                        format!("Unknown binary operator '{}'.", operator.lexeme),
+                        operator.clone(),
+                    )),
+                }
+            }
+        }
+    }
+
+    // type checks for the arithmetic and comparison operators
+
+    fn number_operand(operator: &Token, operand: &Value) -> Result<f64, RuntimeError> {
+        match operand {
+            Value::Number(number) => Ok(*number),
+            _ => Err(RuntimeError::new(
+                ErrorCode::OperandType,
+                "Operand must be a number.".to_string(),
+                operator.clone(),
+            )),
+        }
+    }
+
+    fn number_operands(
+        operator: &Token,
+        left: &Value,
+        right: &Value,
+    ) -> Result<(f64, f64), RuntimeError> {
+        match (left, right) {
+            (Value::Number(left), Value::Number(right)) => Ok((*left, *right)),
+            _ => Err(RuntimeError::new(
+                ErrorCode::OperandType,
+                "Operands must be numbers.".to_string(),
+                operator.clone(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::resolver::Resolver;
+    use crate::scanner::Scanner;
+
+    fn evaluate(source: &str) -> Result<Value, RuntimeError> {
+        let mut scanner = Scanner::new(source.to_string());
+        scanner.scan_tokens();
+        let mut parser = Parser::new(scanner.tokens);
+        let expressions = parser.parse_expressions().unwrap();
+
+        Interpreter::new().evaluate(&expressions[0])
+    }
+
+    // runs `program` and then evaluates `expression` against the resulting state
+    fn run_then_evaluate(program: &str, expression: &str) -> Result<Value, RuntimeError> {
+        run_then_evaluate_with(&mut Interpreter::new(), program, expression)
+    }
+
+    fn run_then_evaluate_with(
+        interpreter: &mut Interpreter,
+        program: &str,
+        expression: &str,
+    ) -> Result<Value, RuntimeError> {
+        let mut scanner = Scanner::new(program.to_string());
+        scanner.scan_tokens();
+        let program = Parser::new(scanner.tokens).parse().unwrap();
+        Resolver::new(interpreter).resolve(&program.statements);
+        interpreter.interpret(&program.statements)?;
+
+        let mut scanner = Scanner::new(expression.to_string());
+        scanner.scan_tokens();
+        let expressions = Parser::new(scanner.tokens).parse_expressions().unwrap();
+        interpreter.evaluate(&expressions[0])
+    }
+
+    #[test]
+    fn test_evaluate_arithmetic() {
+        assert_eq!(evaluate("(1 + 2) * 3 - 4 / 2").unwrap(), Value::Number(7.0));
+        assert_eq!(evaluate("-(3)").unwrap(), Value::Number(-3.0));
+    }
+
+    #[test]
+    fn test_evaluate_string_concatenation() {
+        assert_eq!(
+            evaluate("\"foo\" + \"bar\"").unwrap(),
+            Value::String("foobar".to_string())
+        );
+    }
+
+    #[test]
+    fn test_evaluate_string_interpolation() {
+        assert_eq!(
+            evaluate(r#""${1 + 2} is ${"odd"}, ${nil}${true}""#).unwrap(),
+            Value::String("3 is odd, niltrue".to_string())
+        );
+        // nested interpolations and braces inside strings
+        assert_eq!(
+            evaluate(r#""<${"[${"}"}]"}>""#).unwrap(),
+            Value::String("<[}]>".to_string())
+        );
+    }
+
+    #[test]
+    fn test_evaluate_truthiness_and_equality() {
+        assert_eq!(evaluate("!nil").unwrap(), Value::Boolean(true));
+        assert_eq!(evaluate("!0").unwrap(), Value::Boolean(false));
+        assert_eq!(evaluate("1 == \"1\"").unwrap(), Value::Boolean(false));
+        assert_eq!(evaluate("nil == nil").unwrap(), Value::Boolean(true));
+        assert_eq!(evaluate("2 >= 2").unwrap(), Value::Boolean(true));
+    }
+
+    #[test]
+    fn test_evaluate_runtime_errors() {
+        let error = evaluate("-\"muffin\"").unwrap_err();
+        assert_eq!(error.message, "Operand must be a number.");
+
+        let error = evaluate("1 < true").unwrap_err();
+        assert_eq!(error.message, "Operands must be numbers.");
+
+        let error = evaluate("\"a\" + 1").unwrap_err();
+        assert_eq!(
+            error.message,
+            "Operands must be two numbers or two strings."
+        );
+        assert_eq!(
+            error.to_string(),
+            "Operands must be two numbers or two strings.\n[line 1]"
+        );
+    }
+
+    #[test]
+    fn test_var_declarations() {
+        assert_eq!(
+            run_then_evaluate("var a = 1; var b = a + 2;", "b").unwrap(),
+            Value::Number(3.0)
+        );
+        assert_eq!(run_then_evaluate("var b;", "b").unwrap(), Value::Nil);
+    }
+
+    #[test]
+    fn test_names_compare_in_normal_form() {
+        // precomposed and decomposed spellings, in globals and in a local scope
+        let program = "var e\u{301} = 1; fun f() { var \u{e9} = 2; return e\u{301}; } var g = f();";
+        assert_eq!(
+            run_then_evaluate(program, "\u{e9} + g").unwrap(),
+            Value::Number(3.0)
+        );
+    }
+
+    #[test]
+    fn test_undefined_variable() {
+        let error = run_then_evaluate("print 1;", "missing").unwrap_err();
+        assert_eq!(error.message, "Undefined variable 'missing'.");
+    }
+
+    #[test]
+    fn test_block_scoping_and_assignment() {
+        let program = "
+            var a = \"global a\";
+            var b = \"global b\";
+            var c = 0;
+            {
+                var a = \"outer a\";
+                b = \"assigned b\";
+                {
+                    var a = \"inner a\";
+                    c = a;
+                }
+            }
+        ";
+
+        assert_eq!(
+            run_then_evaluate(program, "a").unwrap(),
+            Value::String("global a".to_string())
+        );
+        assert_eq!(
+            run_then_evaluate(program, "b").unwrap(),
+            Value::String("assigned b".to_string())
+        );
+        assert_eq!(
+            run_then_evaluate(program, "c").unwrap(),
+            Value::String("inner a".to_string())
+        );
+    }
+
+    #[test]
+    fn test_assignment_to_undefined_variable() {
+        let error = run_then_evaluate("{ missing = 1; }", "nil").unwrap_err();
+        assert_eq!(error.message, "Undefined variable 'missing'.");
+    }
+
+    #[test]
+    fn test_logical_operators_return_operands() {
+        assert_eq!(
+            evaluate("\"hi\" or 2").unwrap(),
+            Value::String("hi".to_string())
+        );
+        assert_eq!(
+            evaluate("nil or \"yes\"").unwrap(),
+            Value::String("yes".to_string())
+        );
+        assert_eq!(evaluate("nil and missing").unwrap(), Value::Nil);
+        assert_eq!(evaluate("1 and 2").unwrap(), Value::Number(2.0));
+    }
+
+    #[test]
+    fn test_control_flow() {
+        let program = "
+            var result = 0;
+            for (var i = 0; i < 5; i = i + 1) {
+                if (i == 2) result = result + 100; else result = result + i;
+            }
+            var n = 0;
+            while (n < 3) n = n + 1;
+        ";
+
+        assert_eq!(
+            run_then_evaluate(program, "result").unwrap(),
+            Value::Number(108.0)
+        );
+        assert_eq!(run_then_evaluate(program, "n").unwrap(), Value::Number(3.0));
+        // the loop variable is scoped to the for statement
+        assert!(run_then_evaluate(program, "i").is_err());
+    }
+
+    #[test]
+    fn test_functions_and_return() {
+        let program = "
+            fun fib(n) {
+                if (n < 2) return n;
+                return fib(n - 2) + fib(n - 1);
+            }
+            fun noop() {}
+            var result = fib(10);
+        ";
+
+        assert_eq!(
+            run_then_evaluate(program, "result").unwrap(),
+            Value::Number(55.0)
+        );
+        assert_eq!(run_then_evaluate(program, "noop()").unwrap(), Value::Nil);
+        assert_eq!(
+            run_then_evaluate(program, "fib(1, 2)").unwrap_err().message,
+            "Expected 1 arguments but got 2."
+        );
+        assert_eq!(
+            run_then_evaluate(program, "result()").unwrap_err().message,
+            "Can only call functions and classes."
+        );
+    }
+
+    #[test]
+    fn test_closures_capture_their_environment() {
+        let program = "
+            fun makeCounter() {
+                var i = 0;
+                fun count() {
+                    i = i + 1;
+                    return i;
+                }
+                return count;
+            }
+            var counter = makeCounter();
+            counter();
+            counter();
+        ";
+
+        assert_eq!(
+            run_then_evaluate(program, "counter()").unwrap(),
+            Value::Number(3.0)
+        );
+    }
+
+    #[test]
+    fn test_classes_fields_and_methods() {
+        let program = "
+            class Counter {
+                init(start) {
+                    this.count = start;
+                }
+                increment() {
+                    this.count = this.count + 1;
+                    return this;
+                }
+            }
+            var counter = Counter(10);
+            counter.increment().increment();
+            var method = counter.increment;
+            method();
+        ";
+
+        assert_eq!(
+            run_then_evaluate(program, "counter.count").unwrap(),
+            Value::Number(13.0)
+        );
+        assert_eq!(
+            run_then_evaluate(program, "counter.init(0) == counter").unwrap(),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            run_then_evaluate(program, "counter.missing")
+                .unwrap_err()
+                .message,
+            "Undefined property 'missing'."
+        );
+        assert_eq!(
+            run_then_evaluate(program, "Counter()").unwrap_err().message,
+            "Expected 1 arguments but got 0."
+        );
+    }
+
+    #[test]
+    fn test_inheritance_and_super() {
+        let program = "
+            class A {
+                method() { return \"A method\"; }
+                name() { return \"A\"; }
+            }
+            class B < A {
+                method() { return \"B then \" + super.method(); }
+            }
+            class C < B {}
+            var c = C();
+        ";
+
+        assert_eq!(
+            run_then_evaluate(program, "c.method()").unwrap(),
+            Value::String("B then A method".to_string())
+        );
+        assert_eq!(
+            run_then_evaluate(program, "c.name()").unwrap(),
+            Value::String("A".to_string())
+        );
+        assert_eq!(
+            run_then_evaluate("var NotAClass = 1; class D < NotAClass {}", "nil")
+                .unwrap_err()
+                .message,
+            "Superclass must be a class."
+        );
+    }
+
+    #[test]
+    fn test_closures_bind_to_the_declaration_in_scope() {
+        let program = "
+            var a = \"global\";
+            var first;
+            var second;
+            {
+                fun showA() {
+                    return a;
+                }
+                first = showA();
+                var a = \"block\";
+                second = showA();
+            }
+        ";
+
+        assert_eq!(
+            run_then_evaluate(program, "first == second").unwrap(),
+            Value::Boolean(true)
+        );
+    }
+
+    #[test]
+    fn test_runaway_recursion_is_a_runtime_error() {
+        let mut interpreter = Interpreter::new();
+        interpreter.max_call_depth = 16;
+
+        assert!(interpreter
+            .run("fun f(n) { if (n > 0) f(n - 1); } f(15);")
+            .is_ok());
+        // reported at the call that goes too deep, not at the function it calls
+        let errors = interpreter.run("fun g() {\n  g();\n}\ng();").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].to_string(), "Stack overflow.\n[line 2]");
+        assert_eq!(errors[0].exit_code(), 70);
+        // the depth is unwound with the error, so the session goes on
+        assert!(interpreter.run("f(15);").is_ok());
+    }
+
+    #[test]
+    fn test_call_stack_size_covers_deep_recursion() {
+        const DEPTH: usize = 1000;
+
+        let program = "
+            fun f(n) {
+                for (var i = 0; i < 1; i = i + 1) {
+                    while (true) {
+                        if (n == 0) return 0;
+                        { return n + f(n - 1); }
+                    }
+                }
+            }
+            var total = f(999);
+        ";
+        let total = std::thread::Builder::new()
+            .stack_size((DEPTH + 16) * CALL_STACK_SIZE)
+            .spawn(move || {
+                let mut interpreter = Interpreter::new();
+                interpreter.max_call_depth = DEPTH;
+                // values hold Rc, so only what they print leaves the thread
+                run_then_evaluate_with(&mut interpreter, program, "total")
+                    .map(|value| value.to_string())
+                    .map_err(|error| error.to_string())
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(total.unwrap(), "499500");
+    }
+}
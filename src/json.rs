@@ -0,0 +1,440 @@
+use crate::diagnostic::{Diagnostic, Label};
+use crate::domain::{Expression, LineIndex, Literal, Span, Token};
+
+/*
+    Machine readable output for `--format json`. Field names are stable within a
+    schema version, any change to them bumps SCHEMA_VERSION.
+
+    Schema version 1, every document is a single object:
+
+        tokenize   { "schema_version": 1, "tokens": [Token], "diagnostics": [Diagnostic] }
+        parse      { "schema_version": 1, "ast": [Expression] | null, "diagnostics": [Diagnostic] }
+                   both on stdout, also when the source can't be read, which gives no
+                   tokens and a null ast
+        evaluate,
+        run        { "schema_version": 1, "diagnostics": [Diagnostic] }, on stderr and only
+                   when there are diagnostics, program output is unchanged
+
+        Token       { "type": "LEFT_PAREN", "lexeme": "(", "literal": null | string | number | bool,
+                      "line": 1, "column": 1, "span": Span }
+        Span        { "start": 0, "end": 1 }, byte offsets, end exclusive
+        Expression  { "kind": ..., "span": Span, ...fields of the kind }
+                      assign        { "name", "value" }
+                      binary        { "operator", "left", "right" }
+                      call          { "callee", "arguments" }
+                      get           { "object", "name" }
+                      grouping      { "expression" }
+                      interpolation { "parts" }, string segments are literal parts
+                      literal       { "value" }
+                      logical       { "operator", "left", "right" }
+                      set           { "object", "name", "value" }
+                      super         { "method" }
+                      this          {}
+                      unary         { "operator", "right" }
+                      variable      { "name" }
+        Diagnostic  { "severity": "error", "code": "E0001" | null, "message", "line",
+                      "column", "span": Span, "labels": [{ "message", "span": Span }],
+                      "notes": [string], "help": string | null }, errors that aren't
+                      about the source, such as a file that can't be read, have a null
+                      line, column and span
+
+    A diagnostic's code names the kind of error, see ErrorCode, and stays the same
+    when its message is reworded.
+
+    Lines and columns are 1-based and columns count characters. A token's or a
+    diagnostic's line and column are those of the start of its span.
+*/
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    // fields keep their insertion order so the output is stable
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object<const N: usize>(fields: [(&str, Json); N]) -> Self {
+        Json::Object(
+            fields
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+        )
+    }
+
+    // a top level document, tagged with the schema version
+    pub fn document<const N: usize>(fields: [(&str, Json); N]) -> Self {
+        let mut document = vec![(
+            "schema_version".to_string(),
+            Json::Number(SCHEMA_VERSION as f64),
+        )];
+        document.extend(
+            fields
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value)),
+        );
+        Json::Object(document)
+    }
+
+    pub fn from_diagnostic(diagnostic: &Diagnostic, source: &str, index: &LineIndex) -> Self {
+        let position = diagnostic
+            .span
+            .map(|span| index.line_column(source, span.start));
+
+        Json::object([
+            ("severity", Json::from("error")),
+            (
+                "code",
+                diagnostic
+                    .code
+                    .map_or(Json::Null, |code| Json::from(code.to_string().as_str())),
+            ),
+            ("message", Json::from(diagnostic.message.as_str())),
+            (
+                "line",
+                position.map_or(Json::Null, |(line, _)| Json::from(line)),
+            ),
+            (
+                "column",
+                position.map_or(Json::Null, |(_, column)| Json::from(column)),
+            ),
+            ("span", diagnostic.span.map_or(Json::Null, Json::from)),
+            (
+                "labels",
+                Json::Array(diagnostic.labels.iter().map(Json::from).collect()),
+            ),
+            (
+                "notes",
+                Json::Array(
+                    diagnostic
+                        .notes
+                        .iter()
+                        .map(|note| Json::from(note.as_str()))
+                        .collect(),
+                ),
+            ),
+            (
+                "help",
+                diagnostic.help.as_deref().map_or(Json::Null, Json::from),
+            ),
+        ])
+    }
+}
+
+fn write_string(f: &mut std::fmt::Formatter<'_>, value: &str) -> std::fmt::Result {
+    write!(f, "\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl std::fmt::Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            // JSON has no representation for NaN or infinities
+            Json::Number(value) if !value.is_finite() => write!(f, "null"),
+            Json::Number(value) => write!(f, "{}", value),
+            Json::String(value) => write_string(f, value),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (index, (name, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, name)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<u32> for Json {
+    fn from(value: u32) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<Span> for Json {
+    fn from(span: Span) -> Self {
+        Json::object([
+            ("start", Json::from(span.start)),
+            ("end", Json::from(span.end)),
+        ])
+    }
+}
+
+impl From<&Label> for Json {
+    fn from(label: &Label) -> Self {
+        Json::object([
+            ("message", Json::from(label.message.as_str())),
+            ("span", Json::from(label.span)),
+        ])
+    }
+}
+
+impl From<&Literal> for Json {
+    fn from(literal: &Literal) -> Self {
+        match literal {
+            Literal::String(value) => Json::from(value.as_str()),
+            Literal::Number(value) => Json::Number(*value),
+            Literal::Boolean(value) => Json::Bool(*value),
+            Literal::Nil => Json::Null,
+        }
+    }
+}
+
+impl From<&Token> for Json {
+    fn from(token: &Token) -> Self {
+        Json::object([
+            ("type", Json::String(token.token_type.to_string())),
+            ("lexeme", Json::from(&*token.lexeme)),
+            (
+                "literal",
+                token.literal.as_ref().map_or(Json::Null, Json::from),
+            ),
+            ("line", Json::from(token.start_line)),
+            ("column", Json::from(token.column)),
+            ("span", Json::from(token.span)),
+        ])
+    }
+}
+
+impl From<&Expression> for Json {
+    fn from(expression: &Expression) -> Self {
+        let node = |expression: &Expression| Json::from(expression);
+        let name = |token: &Token| Json::from(&*token.lexeme);
+
+        let (kind, fields) = match expression {
+            Expression::Assign { name: n, value, .. } => {
+                ("assign", vec![("name", name(n)), ("value", node(value))])
+            }
+            Expression::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } => (
+                "binary",
+                vec![
+                    ("operator", name(operator)),
+                    ("left", node(left)),
+                    ("right", node(right)),
+                ],
+            ),
+            Expression::Call {
+                callee, arguments, ..
+            } => (
+                "call",
+                vec![
+                    ("callee", node(callee)),
+                    (
+                        "arguments",
+                        Json::Array(arguments.iter().map(Json::from).collect()),
+                    ),
+                ],
+            ),
+            Expression::Get {
+                object, name: n, ..
+            } => ("get", vec![("object", node(object)), ("name", name(n))]),
+            Expression::Grouping { expression, .. } => {
+                ("grouping", vec![("expression", node(expression))])
+            }
+            Expression::Interpolation { parts, .. } => (
+                "interpolation",
+                vec![("parts", Json::Array(parts.iter().map(Json::from).collect()))],
+            ),
+            Expression::Literal { value, .. } => ("literal", vec![("value", Json::from(value))]),
+            Expression::Logical {
+                left,
+                operator,
+                right,
+                ..
+            } => (
+                "logical",
+                vec![
+                    ("operator", name(operator)),
+                    ("left", node(left)),
+                    ("right", node(right)),
+                ],
+            ),
+            Expression::Set {
+                object,
+                name: n,
+                value,
+                ..
+            } => (
+                "set",
+                vec![
+                    ("object", node(object)),
+                    ("name", name(n)),
+                    ("value", node(value)),
+                ],
+            ),
+            Expression::Super { method, .. } => ("super", vec![("method", name(method))]),
+            Expression::This { .. } => ("this", vec![]),
+            Expression::Unary {
+                operator, right, ..
+            } => (
+                "unary",
+                vec![("operator", name(operator)), ("right", node(right))],
+            ),
+            Expression::Variable { name: n, .. } => ("variable", vec![("name", name(n))]),
+        };
+
+        let mut object = vec![
+            ("kind".to_string(), Json::from(kind)),
+            ("span".to_string(), Json::from(expression.span())),
+        ];
+        object.extend(
+            fields
+                .into_iter()
+                .map(|(field, value)| (field.to_string(), value)),
+        );
+        Json::Object(object)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn test_escapes_strings() {
+        let json = Json::from("a \"quoted\"\\path\n\u{1}");
+
+        assert_eq!(json.to_string(), r#""a \"quoted\"\\path\n\u0001""#);
+    }
+
+    #[test]
+    fn test_tokens() {
+        let mut scanner = Scanner::new("(\"hi\" 12.5".to_string());
+        scanner.scan_tokens();
+        let tokens = Json::Array(scanner.tokens.iter().map(Json::from).collect());
+
+        assert_eq!(
+            tokens.to_string(),
+            concat!(
+                r#"[{"type":"LEFT_PAREN","lexeme":"(","literal":null,"line":1,"column":1,"span":{"start":0,"end":1}},"#,
+                r#"{"type":"STRING","lexeme":"\"hi\"","literal":"hi","line":1,"column":2,"span":{"start":1,"end":5}},"#,
+                r#"{"type":"NUMBER","lexeme":"12.5","literal":12.5,"line":1,"column":7,"span":{"start":6,"end":10}},"#,
+                r#"{"type":"EOF","lexeme":"","literal":null,"line":1,"column":11,"span":{"start":10,"end":10}}]"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_expression() {
+        let mut scanner = Scanner::new("-a.b(1)".to_string());
+        scanner.scan_tokens();
+        let expressions = Parser::new(scanner.tokens).parse_expressions().unwrap();
+
+        assert_eq!(
+            Json::from(&expressions[0]).to_string(),
+            concat!(
+                r#"{"kind":"unary","span":{"start":0,"end":7},"operator":"-","right":"#,
+                r#"{"kind":"call","span":{"start":1,"end":7},"callee":"#,
+                r#"{"kind":"get","span":{"start":1,"end":4},"object":"#,
+                r#"{"kind":"variable","span":{"start":1,"end":2},"name":"a"},"name":"b"},"#,
+                r#""arguments":[{"kind":"literal","span":{"start":5,"end":6},"value":1}]}}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let mut scanner = Scanner::new(r#""a ${b}""#.to_string());
+        scanner.scan_tokens();
+        let expressions = Parser::new(scanner.tokens).parse_expressions().unwrap();
+
+        assert_eq!(
+            Json::from(&expressions[0]).to_string(),
+            concat!(
+                r#"{"kind":"interpolation","span":{"start":0,"end":8},"parts":["#,
+                r#"{"kind":"literal","span":{"start":0,"end":5},"value":"a "},"#,
+                r#"{"kind":"variable","span":{"start":5,"end":6},"name":"b"}]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_document_and_diagnostic() {
+        let source = "1;\n @";
+        let diagnostic = Diagnostic::new("Unexpected character: @".to_string(), Span::new(4, 5))
+            .with_code(ErrorCode::UnexpectedCharacter)
+            .with_help("remove it".to_string());
+        let document = Json::document([(
+            "diagnostics",
+            Json::Array(vec![Json::from_diagnostic(
+                &diagnostic,
+                source,
+                &LineIndex::new(source),
+            )]),
+        )]);
+
+        assert_eq!(
+            document.to_string(),
+            concat!(
+                r#"{"schema_version":1,"diagnostics":[{"severity":"error","code":"E0001","#,
+                r#""message":"Unexpected character: @","#,
+                r#""line":2,"column":2,"span":{"start":4,"end":5},"labels":[],"notes":[],"help":"remove it"}]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_without_span() {
+        let diagnostic = Diagnostic::without_span("Failed to read file a.lox".to_string());
+
+        assert_eq!(
+            Json::from_diagnostic(&diagnostic, "", &LineIndex::new("")).to_string(),
+            concat!(
+                r#"{"severity":"error","code":null,"message":"Failed to read file a.lox","line":null,"#,
+                r#""column":null,"span":null,"labels":[],"notes":[],"help":null}"#
+            )
+        );
+    }
+}
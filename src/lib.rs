@@ -1,3 +1,14 @@
+pub mod class;
+pub mod diagnostic;
 pub mod domain;
+pub mod environment;
+pub mod error;
+pub mod formatter;
+pub mod function;
+pub mod interpreter;
+pub mod json;
 pub mod parser;
+pub mod resolver;
 pub mod scanner;
+pub mod syntax;
+pub mod unicode;
@@ -1,95 +1,342 @@
+mod cli;
+mod repl;
+
+use cli::{Action, Color, Command, Format, Options, Source};
+use interpreter_starter_rust::diagnostic::Diagnostic;
+use interpreter_starter_rust::domain::{Expression, LineIndex};
+use interpreter_starter_rust::error::LoxError;
+use interpreter_starter_rust::formatter::{self, FormatOptions};
+use interpreter_starter_rust::interpreter::{self, Interpreter};
+use interpreter_starter_rust::json::Json;
 use interpreter_starter_rust::parser::Parser;
-use interpreter_starter_rust::scanner::Scanner;
+use interpreter_starter_rust::scanner::Lexer;
 use std::env;
-use std::fs;
-use std::io::{self, Write};
 use std::process::ExitCode;
 
-fn main() -> ExitCode {
-    let args: Vec<String> = env::args().collect();
-    let mut exit_code = ExitCode::SUCCESS;
+struct Reporter<'a> {
+    format: Format,
+    // whether pretty diagnostics are colored, see `Color`
+    colors: bool,
+    source: &'a str,
+    // built once and shared by every diagnostic reported against the source
+    index: LineIndex,
+    filename: &'a str,
+    // collected for the json document instead of being printed one by one
+    diagnostics: Vec<Json>,
+    // the exit code of the first error reported
+    exit_code: Option<u8>,
+}
 
-    if args.len() < 3 {
-        writeln!(io::stderr(), "Usage: {} tokenize <filename>", args[0]).unwrap();
-        return exit_code;
+impl<'a> Reporter<'a> {
+    fn new(format: Format, color: Color, source: &'a str, filename: &'a str) -> Self {
+        Self {
+            format,
+            colors: color.enabled(),
+            source,
+            index: LineIndex::new(source),
+            filename,
+            diagnostics: Vec::new(),
+            exit_code: None,
+        }
     }
 
-    let command = &args[1];
-    let filename = &args[2];
+    fn report(&mut self, error: impl Into<LoxError>) {
+        let error = error.into();
+        self.fail(error.exit_code());
 
-    match command.as_str() {
-        "tokenize" => {
-            // You can use print statements as follows for debugging, they'll be visible when running tests.
-            writeln!(io::stderr(), "Logs from your program will appear here!").unwrap();
+        match self.format {
+            Format::Text => eprintln!("{}", error),
+            Format::Pretty => eprint!(
+                "{}",
+                Diagnostic::from(&error).render(
+                    self.source,
+                    &self.index,
+                    self.filename,
+                    self.colors
+                )
+            ),
+            Format::Json => self.diagnostics.push(Json::from_diagnostic(
+                &Diagnostic::from(&error),
+                self.source,
+                &self.index,
+            )),
+        }
+    }
 
-            let file_contents = fs::read_to_string(filename).unwrap_or_else(|_| {
-                writeln!(io::stderr(), "Failed to read file {}", filename).unwrap();
-                String::new()
-            });
+    // for failures that aren't errors in the source
+    fn fail(&mut self, exit_code: u8) {
+        self.exit_code.get_or_insert(exit_code);
+    }
 
-            // Uncomment this block to pass the first stage
-            if !file_contents.is_empty() {
-                let mut scanner = Scanner::new(file_contents);
-                scanner.scan_tokens();
+    fn take_diagnostics(&mut self) -> Json {
+        Json::Array(std::mem::take(&mut self.diagnostics))
+    }
 
-                if !scanner.errors.is_empty() {
-                    exit_code = ExitCode::from(65);
+    // prints the json document for commands whose stdout belongs to the program,
+    // and hands back the exit code if anything went wrong
+    fn finish(mut self) -> Option<u8> {
+        if self.format == Format::Json && !self.diagnostics.is_empty() {
+            eprintln!(
+                "{}",
+                Json::document([("diagnostics", self.take_diagnostics())])
+            );
+        }
+
+        self.exit_code
+    }
+}
+
+// runs one source through the command, the interpreter is shared by all sources,
+// path is the file the source was read from
+fn execute(
+    command: Command,
+    path: Option<&str>,
+    reporter: &mut Reporter,
+    interpreter: &mut Interpreter,
+) {
+    let source = reporter.source;
+
+    match command {
+        Command::Tokenize => {
+            // tokens are printed as they are scanned, errors reported where they occur
+            let mut tokens = Vec::new();
+            for scanned in Lexer::new(source) {
+                match scanned {
+                    Ok(token) if reporter.format == Format::Json => tokens.push(Json::from(&token)),
+                    Ok(token) => println!("{}", token),
+                    Err(error) => reporter.report(error),
                 }
+            }
 
-                for error in scanner.errors {
-                    eprintln!("{}", error);
+            if reporter.format == Format::Json {
+                println!(
+                    "{}",
+                    Json::document([
+                        ("tokens", Json::Array(tokens)),
+                        ("diagnostics", reporter.take_diagnostics()),
+                    ])
+                );
+            }
+        }
+        Command::Parse => {
+            let expressions = parse_expressions(reporter);
+
+            if reporter.format == Format::Json {
+                let ast = expressions.map_or(Json::Null, |expressions| {
+                    Json::Array(expressions.iter().map(Json::from).collect())
+                });
+                println!(
+                    "{}",
+                    Json::document([("ast", ast), ("diagnostics", reporter.take_diagnostics())])
+                );
+            } else {
+                for expression in expressions.into_iter().flatten() {
+                    println!("{}", expression);
+                }
+            }
+        }
+        Command::Evaluate => {
+            for expression in parse_expressions(reporter).into_iter().flatten() {
+                match interpreter.evaluate(&expression) {
+                    Ok(value) => println!("{}", value),
+                    Err(error) => {
+                        reporter.report(error);
+                        break;
+                    }
+                }
+            }
+        }
+        Command::Run => {
+            if let Err(errors) = interpreter.run(source) {
+                for error in errors {
+                    reporter.report(error);
+                }
+            }
+        }
+        Command::Fmt { check, width } => {
+            // a source with errors is reported and left as it is
+            let formatted = match formatter::format(source, &FormatOptions { width }) {
+                Ok(formatted) => formatted,
+                Err(errors) => {
+                    for error in errors {
+                        reporter.report(error);
+                    }
+                    return;
                 }
+            };
 
-                for token in scanner.tokens {
-                    println!("{}", token);
+            if check {
+                if formatted != source {
+                    eprintln!("{} is not formatted", reporter.filename);
+                    reporter.fail(1);
+                }
+            } else if let Some(path) = path {
+                if formatted != source {
+                    if let Err(error) = std::fs::write(path, formatted) {
+                        eprintln!("Failed to write file {}: {}", path, error);
+                        reporter.fail(74);
+                    }
                 }
             } else {
-                println!("EOF  null"); // Placeholder, remove this line when implementing the scanner
+                print!("{}", formatted);
             }
         }
-        "parse" => {
-            let file_contents = fs::read_to_string(filename).unwrap_or_else(|_| {
-                writeln!(io::stderr(), "Failed to read file {}", filename).unwrap();
-                String::new()
-            });
+    }
+}
+
+// The parser pulls tokens straight from the lexer, scanning errors are set aside and
+// take precedence over the syntax errors they cause. Returns the expressions when
+// there were no errors, the errors are reported.
+fn parse_expressions(reporter: &mut Reporter) -> Option<Vec<Expression>> {
+    let mut scanner_errors = Vec::new();
+    let tokens = Lexer::new(reporter.source)
+        .filter_map(|scanned| scanned.map_err(|error| scanner_errors.push(error)).ok());
+    let parsed = Parser::new(tokens).parse_expressions();
 
-            if !file_contents.is_empty() {
-                // Scan the tokens
-                let mut scanner = Scanner::new(file_contents);
-                scanner.scan_tokens();
+    if !scanner_errors.is_empty() {
+        for error in scanner_errors {
+            reporter.report(error);
+        }
+        return None;
+    }
 
-                // Parse the tokens
-                let mut parser = Parser::new(scanner.tokens);
+    match parsed {
+        Ok(expressions) => Some(expressions),
+        Err(errors) => {
+            for error in errors {
+                reporter.report(error);
+            }
+            None
+        }
+    }
+}
 
-                let parsed_result = parser.parse();
+// tokenize and parse print their json document to stdout, for a source that can't be
+// read it has no tokens or syntax tree, other commands leave it to `Reporter::finish`
+fn unreadable_document(command: Command, reporter: &mut Reporter) -> Option<Json> {
+    let output = match command {
+        Command::Tokenize => ("tokens", Json::Array(Vec::new())),
+        Command::Parse => ("ast", Json::Null),
+        _ => return None,
+    };
+    Some(Json::document([
+        output,
+        ("diagnostics", reporter.take_diagnostics()),
+    ]))
+}
 
-                if !scanner.errors.is_empty() {
-                    exit_code = ExitCode::from(65);
+// Lox calls recurse on the native stack, so the interpreter runs on a thread with a
+// stack deep enough to reach its own call depth limit.
+fn main() -> ExitCode {
+    let thread = std::thread::Builder::new()
+        .stack_size(interpreter::STACK_SIZE)
+        .spawn(run)
+        .expect("failed to start the interpreter thread");
 
-                    for error in &scanner.errors {
-                        eprintln!("{}", error);
-                    }
-                } else if !parser.errors.is_empty() {
-                    exit_code = ExitCode::from(65);
+    thread
+        .join()
+        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+}
 
-                    for error in &parser.errors {
-                        eprintln!("{}", error);
-                    }
-                } else {
-                    for paresd in parsed_result {
-                        println!("{}", paresd);
+fn run() -> ExitCode {
+    let Options {
+        command,
+        format,
+        color,
+        sources,
+    } = match cli::parse_args(env::args().skip(1)) {
+        Ok(Action::Execute(options)) => options,
+        Ok(Action::Help) => {
+            print!("{}", cli::USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Ok(Action::Version) => {
+            println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+            return ExitCode::SUCCESS;
+        }
+        Ok(Action::Repl(format, color)) => {
+            repl::run(format, color);
+            return ExitCode::SUCCESS;
+        }
+        Err(error) => {
+            eprintln!(
+                "{}\n{}\nRun with --help for more information.",
+                error,
+                cli::USAGE.lines().next().unwrap_or_default()
+            );
+            return ExitCode::from(cli::USAGE_EXIT_CODE);
+        }
+    };
+
+    let mut interpreter = Interpreter::new();
+    let mut failed = None;
+
+    // stops at the first source that fails, later ones may depend on it
+    for source in &sources {
+        let contents = match source.read() {
+            Ok(contents) => contents,
+            Err(error) => {
+                let mut reporter = Reporter::new(format, color, "", source.name());
+                reporter.report(LoxError::io(source.name(), error));
+                if format == Format::Json {
+                    if let Some(document) = unreadable_document(command, &mut reporter) {
+                        println!("{}", document);
                     }
                 }
-            } else {
-                writeln!(io::stderr(), "Failed to read file {}", filename).unwrap();
-                return exit_code;
+                return reporter.finish().map_or(ExitCode::FAILURE, ExitCode::from);
             }
-        }
-        _ => {
-            writeln!(io::stderr(), "Unknown command: {}", command).unwrap();
-            return exit_code;
+        };
+
+        let path = match source {
+            Source::File(path) => Some(path.as_str()),
+            Source::Stdin | Source::Inline(_) => None,
+        };
+        let mut reporter = Reporter::new(format, color, &contents, source.name());
+        execute(command, path, &mut reporter, &mut interpreter);
+
+        if let Some(exit_code) = reporter.finish() {
+            // sources are formatted one by one, so the rest are still formatted
+            if matches!(command, Command::Fmt { .. }) {
+                failed.get_or_insert(exit_code);
+                continue;
+            }
+            return ExitCode::from(exit_code);
         }
     }
 
-    exit_code
+    failed.map_or(ExitCode::SUCCESS, ExitCode::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unreadable(command: Command) -> (Option<String>, Reporter<'static>) {
+        let mut reporter = Reporter::new(Format::Json, Color::Never, "", "a.lox");
+        let error = std::io::Error::from(std::io::ErrorKind::NotFound);
+        reporter.report(LoxError::io("a.lox", error));
+        let document = unreadable_document(command, &mut reporter);
+        (document.map(|document| document.to_string()), reporter)
+    }
+
+    #[test]
+    fn test_unreadable_source_document_goes_where_the_command_puts_it() {
+        let (document, reporter) = unreadable(Command::Tokenize);
+        assert!(document.unwrap().starts_with(
+            r#"{"schema_version":1,"tokens":[],"diagnostics":[{"severity":"error","code":"E0401","#
+        ));
+        // nothing is left for stderr
+        assert!(reporter.diagnostics.is_empty());
+        assert_eq!(reporter.finish(), Some(74));
+
+        let (document, reporter) = unreadable(Command::Parse);
+        assert!(document
+            .unwrap()
+            .starts_with(r#"{"schema_version":1,"ast":null,"diagnostics":[{"#));
+        assert!(reporter.diagnostics.is_empty());
+
+        let (document, reporter) = unreadable(Command::Run);
+        assert_eq!(document, None);
+        assert_eq!(reporter.diagnostics.len(), 1);
+    }
 }
@@ -1,74 +1,237 @@
-use crate::domain::{token::Token, Expression, Literal, TokenType};
+// ParserError carries a whole token plus labels, it is only built on the error path
+#![allow(clippy::result_large_err)]
 
+use std::rc::Rc;
+
+use crate::diagnostic::Label;
+use crate::domain::statement::{FunctionDeclaration, Program};
+use crate::domain::{token::Token, Expression, Literal, Span, Statement, TokenType};
+use crate::error::ErrorCode;
+use crate::syntax::{SyntaxKind, SyntaxNode, TreeBuilder};
+
+// the reference implementation limits calls and declarations to 255 arguments
+const MAX_ARGUMENTS: usize = 255;
+
+// Nested expressions and statements are parsed recursively, so how deep they go is
+// limited to turn a pathological source into a syntax error instead of a crash.
+pub const MAX_NESTING_DEPTH: usize = 256;
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("[line {}] Error{}: {}", .token.line, .token.error_location(), .message)]
 pub struct ParserError {
+    pub code: ErrorCode,
     pub message: String,
     pub token: Token,
+    // related code and hints, only shown by the rich diagnostic renderer
+    pub labels: Vec<Label>,
+    pub help: Option<String>,
 }
 
 impl ParserError {
-    pub fn new(message: String, token: Token) -> Self {
-        Self { message, token }
+    pub fn new(code: ErrorCode, message: String, token: Token) -> Self {
+        Self {
+            code,
+            message,
+            token,
+            labels: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn with_label(mut self, span: Span, message: String) -> Self {
+        self.labels.push(Label::new(span, message));
+        self
     }
-}
 
-impl std::fmt::Display for ParserError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "[line {}] Error: {}", self.token.line, self.message)
+    pub fn with_help(mut self, help: String) -> Self {
+        self.help = Some(help);
+        self
     }
 }
 
-pub struct Parser {
-    pub tokens: Vec<Token>,
-    pub current: usize,
-    pub errors: Vec<ParserError>,
+// An Err unwinds the parser to the nearest statement boundary, see `declaration`.
+type ParseResult<T> = Result<T, ParserError>;
+
+// Tokens are pulled from the iterator as the parser advances, so a Lexer can feed it
+// directly without the whole token stream ever being held in memory.
+pub struct Parser<'a> {
+    tokens: Box<dyn Iterator<Item = Token> + 'a>,
+    // the token being looked at, consumed tokens are handed out by `advance`
+    current: Token,
+    errors: Vec<ParserError>,
+    // only built when asked for, see `parse_with_syntax_tree`
+    tree: Option<TreeBuilder>,
+    // how deep the expressions and statements being parsed are nested, see `nested`
+    depth: usize,
+    pub(crate) max_depth: usize,
+    // set once the source is found to be nested too deeply, see `nested`
+    gave_up: bool,
 }
 
-impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
+impl<'a> Parser<'a> {
+    pub fn new<I>(tokens: I) -> Self
+    where
+        I: IntoIterator<Item = Token>,
+        I::IntoIter: 'a,
+    {
+        let mut tokens = tokens.into_iter();
+        let current = tokens
+            .next()
+            .unwrap_or_else(|| Self::end_of_input(Span::default()));
+
         Self {
-            tokens,
-            current: 0,
+            tokens: Box::new(tokens),
+            current,
             errors: Vec::new(),
+            tree: None,
+            depth: 0,
+            max_depth: MAX_NESTING_DEPTH,
+            gave_up: false,
+        }
+    }
+
+    // keeps parsing after a syntax error so that every error is reported in one run,
+    // but only hands out a program when there were none
+    pub fn parse(&mut self) -> Result<Program, Vec<ParserError>> {
+        let mut statements: Vec<Statement> = Vec::new();
+        while !self.is_at_end() {
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
         }
+
+        self.finish(Program::new(statements))
     }
 
-    pub fn parse(&mut self) -> Vec<Expression> {
+    // also returns the concrete syntax tree of everything consumed, which is lossless
+    // when the tokens come from a Lexer keeping all trivia
+    pub fn parse_with_syntax_tree(&mut self) -> (Result<Program, Vec<ParserError>>, SyntaxNode) {
+        self.tree = Some(TreeBuilder::new());
+        let parsed = self.parse();
+
+        let mut tree = self.tree.take().unwrap_or_default();
+        // the Eof token is never consumed but holds the trivia at the end of the source
+        tree.token(self.current.clone());
+        (parsed, tree.finish())
+    }
+
+    // parses a sequence of bare expressions, used by the `parse` and `evaluate` commands
+    pub fn parse_expressions(&mut self) -> Result<Vec<Expression>, Vec<ParserError>> {
         let mut expressions: Vec<Expression> = Vec::new();
         while !self.is_at_end() {
-            expressions.push(self.expression());
+            match self.expression() {
+                Ok(expression) => expressions.push(expression),
+                Err(error) => self.recover(error),
+            }
+        }
+
+        self.finish(expressions)
+    }
+
+    fn finish<T>(&mut self, parsed: T) -> Result<T, Vec<ParserError>> {
+        if self.errors.is_empty() {
+            Ok(parsed)
+        } else {
+            Err(std::mem::take(&mut self.errors))
         }
-        expressions
     }
 
     // basic methods to help with parsing
 
-    fn advance(&mut self) -> Token {
-        if !self.is_at_end() {
-            self.current += 1;
+    // consumes the current token and hands it out, the Eof token is never consumed
+    fn advance(&mut self) -> Option<Token> {
+        if self.is_at_end() {
+            return None;
+        }
+
+        let next = match self.tokens.next() {
+            Some(token) => token,
+            None => Self::end_of_input(Span::new(self.current.span.end, self.current.span.end)),
+        };
+        let token = std::mem::replace(&mut self.current, next);
+        // the syntax tree keeps its own copy, the AST takes the token
+        if let Some(tree) = &mut self.tree {
+            tree.token(token.clone());
+        }
+        Some(token)
+    }
+
+    // marks where a syntax tree node starts, before its first token is consumed
+    fn checkpoint(&self) -> usize {
+        self.tree.as_ref().map_or(0, TreeBuilder::checkpoint)
+    }
+
+    // makes a node of everything consumed since the checkpoint
+    fn wrap(&mut self, checkpoint: usize, kind: SyntaxKind) {
+        if let Some(tree) = &mut self.tree {
+            tree.wrap(checkpoint, kind);
         }
-        self.previous()
+    }
+
+    // parses something and makes a node of it, whether it parsed or not
+    fn node<T>(
+        &mut self,
+        checkpoint: usize,
+        kind: SyntaxKind,
+        parse: impl FnOnce(&mut Self) -> ParseResult<T>,
+    ) -> ParseResult<T> {
+        let parsed = parse(self);
+        self.wrap(checkpoint, kind);
+        parsed
+    }
+
+    // parses something one level deeper, failing with the given message when that is
+    // deeper than the limit, see MAX_NESTING_DEPTH
+    fn nested<T>(
+        &mut self,
+        message: &str,
+        parse: impl FnOnce(&mut Self) -> ParseResult<T>,
+    ) -> ParseResult<T> {
+        if self.depth >= self.max_depth {
+            let error = ParserError::new(
+                ErrorCode::NestedTooDeeply,
+                message.to_string(),
+                self.peek().clone(),
+            );
+            // there is no recovering inside something that deep, so the rest of the
+            // source is skipped and the errors of everything left open are dropped
+            self.gave_up = true;
+            while self.advance().is_some() {}
+            return Err(error);
+        }
+
+        self.depth += 1;
+        let parsed = parse(self);
+        self.depth -= 1;
+        parsed
+    }
+
+    // stands in for the Eof token of a token stream that ends without one
+    fn end_of_input(span: Span) -> Token {
+        Token::new(TokenType::Eof, String::new(), None, 0, 0, span)
     }
 
     fn is_at_end(&self) -> bool {
-        self.peek().token_type == TokenType::Eof
+        self.current.token_type == TokenType::Eof
     }
 
-    fn peek(&self) -> Token {
-        self.tokens[self.current].clone()
+    fn peek(&self) -> &Token {
+        &self.current
     }
 
-    fn previous(&self) -> Token {
-        self.tokens[self.current - 1].clone()
+    fn advance_for_token_types(&mut self, token_types: &[TokenType]) -> bool {
+        self.take_for_token_types(token_types).is_some()
     }
 
-    fn advance_for_token_types(&mut self, token_types: Vec<TokenType>) -> bool {
-        for token_type in token_types {
-            if self.check_future_for_token(token_type) {
-                self.advance();
-                return true;
-            }
+    // consumes the current token if it is of one of the given types and hands it out
+    fn take_for_token_types(&mut self, token_types: &[TokenType]) -> Option<Token> {
+        if token_types
+            .iter()
+            .any(|&token_type| self.check_future_for_token(token_type))
+        {
+            return self.advance();
         }
-        return false;
+        None
     }
 
     // will be used to check if the current token is of  the expected type
@@ -79,6 +242,344 @@ impl Parser {
         }
         self.peek().token_type == token_type
     }
+
+    // consumes the current token if it is of the expected type,
+    // otherwise fails with the given message
+    fn consume(&mut self, token_type: TokenType, message: &str) -> ParseResult<Token> {
+        if let Some(token) = self.take_for_token_types(&[token_type]) {
+            return Ok(token);
+        }
+
+        Err(ParserError::new(
+            ErrorCode::ExpectedToken,
+            message.to_string(),
+            self.peek().clone(),
+        ))
+    }
+
+    // reports a syntax error and gets ready to parse what follows it
+    fn recover(&mut self, error: ParserError) {
+        if !self.gave_up || error.code == ErrorCode::NestedTooDeeply {
+            self.errors.push(error);
+        }
+        self.synchronize();
+    }
+
+    // panic mode: after an error, discards tokens until we are
+    // probably at the beginning of the next statement
+    fn synchronize(&mut self) {
+        let mut previous = self.advance();
+
+        while !self.is_at_end() {
+            if previous.is_some_and(|token| token.token_type == TokenType::Semicolon) {
+                return;
+            }
+
+            match self.peek().token_type {
+                TokenType::Class
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::For
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Print
+                | TokenType::Return => return,
+                _ => {}
+            }
+
+            previous = self.advance();
+        }
+    }
+}
+
+/*
+
+Statements
+
+Reference - https://craftinginterpreters.com/statements-and-state.html
+
+*/
+
+impl Parser<'_> {
+    // the statement-level entry point, where the parser recovers from syntax errors
+    fn declaration(&mut self) -> Option<Statement> {
+        let checkpoint = self.checkpoint();
+        match self.declaration_or_error() {
+            Ok(statement) => Some(statement),
+            Err(error) => {
+                self.recover(error);
+                // the broken declaration together with the tokens skipped after it
+                self.wrap(checkpoint, SyntaxKind::Error);
+                None
+            }
+        }
+    }
+
+    fn declaration_or_error(&mut self) -> ParseResult<Statement> {
+        // doc comments sit before the keyword starting the declaration
+        let doc = self.peek().doc_comment();
+        let checkpoint = self.checkpoint();
+
+        if self.advance_for_token_types(&[TokenType::Class]) {
+            return self.node(checkpoint, SyntaxKind::ClassDeclaration, |parser| {
+                parser.class_declaration(doc)
+            });
+        }
+        if self.advance_for_token_types(&[TokenType::Fun]) {
+            let declaration = self.node(checkpoint, SyntaxKind::FunctionDeclaration, |parser| {
+                parser.function("function", doc)
+            })?;
+            return Ok(Statement::new_function(declaration));
+        }
+        if self.advance_for_token_types(&[TokenType::Var]) {
+            return self.node(checkpoint, SyntaxKind::VarDeclaration, |parser| {
+                parser.var_declaration(doc)
+            });
+        }
+        self.statement()
+    }
+
+    fn var_declaration(&mut self, doc: Option<String>) -> ParseResult<Statement> {
+        let name = self.consume(TokenType::Identifier, "Expect variable name.")?;
+
+        let mut initializer = None;
+        if self.advance_for_token_types(&[TokenType::Equal]) {
+            initializer = Some(self.expression()?);
+        }
+
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after variable declaration.",
+        )?;
+        Ok(Statement::new_var(name, initializer, doc))
+    }
+
+    fn class_declaration(&mut self, doc: Option<String>) -> ParseResult<Statement> {
+        let name = self.consume(TokenType::Identifier, "Expect class name.")?;
+
+        let mut superclass = None;
+        if self.advance_for_token_types(&[TokenType::Less]) {
+            let checkpoint = self.checkpoint();
+            let superclass_name = self.consume(TokenType::Identifier, "Expect superclass name.")?;
+            superclass = Some(Expression::new_variable(superclass_name));
+            self.wrap(checkpoint, SyntaxKind::Variable);
+        }
+
+        let left_brace = self.consume(TokenType::LeftBrace, "Expect '{' before class body.")?;
+
+        let mut methods = Vec::new();
+        while !self.check_future_for_token(TokenType::RightBrace) && !self.is_at_end() {
+            let method_doc = self.peek().doc_comment();
+            let checkpoint = self.checkpoint();
+            let method = self.node(checkpoint, SyntaxKind::Method, |parser| {
+                parser.function("method", method_doc)
+            })?;
+            methods.push(Rc::new(method));
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after class body.")
+            .map_err(|error| error.with_label(left_brace.span, "opening '{' here".to_string()))?;
+        Ok(Statement::new_class(name, superclass, methods, doc))
+    }
+
+    fn function(&mut self, kind: &str, doc: Option<String>) -> ParseResult<FunctionDeclaration> {
+        let name = self.consume(TokenType::Identifier, &format!("Expect {} name.", kind))?;
+        let parameters = self.checkpoint();
+        let left_paren = self.consume(
+            TokenType::LeftParen,
+            &format!("Expect '(' after {} name.", kind),
+        )?;
+
+        let mut params = Vec::new();
+        if !self.check_future_for_token(TokenType::RightParen) {
+            loop {
+                if params.len() >= MAX_ARGUMENTS {
+                    self.errors.push(ParserError::new(
+                        ErrorCode::TooManyParameters,
+                        format!("Can't have more than {} parameters.", MAX_ARGUMENTS),
+                        self.peek().clone(),
+                    ));
+                }
+                params.push(self.consume(TokenType::Identifier, "Expect parameter name.")?);
+
+                if !self.advance_for_token_types(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after parameters.")
+            .map_err(|error| error.with_label(left_paren.span, "opening '(' here".to_string()))?;
+        self.wrap(parameters, SyntaxKind::ParameterList);
+
+        let checkpoint = self.checkpoint();
+        let body = self.node(checkpoint, SyntaxKind::Block, |parser| {
+            let left_brace = parser.consume(
+                TokenType::LeftBrace,
+                &format!("Expect '{{' before {} body.", kind),
+            )?;
+            parser.nested("Statement nested too deeply.", |parser| {
+                parser.block(left_brace.span)
+            })
+        })?;
+
+        Ok(FunctionDeclaration::new(name, params, body, doc))
+    }
+
+    fn statement(&mut self) -> ParseResult<Statement> {
+        self.nested("Statement nested too deeply.", Self::nested_statement)
+    }
+
+    fn nested_statement(&mut self) -> ParseResult<Statement> {
+        let checkpoint = self.checkpoint();
+
+        if self.advance_for_token_types(&[TokenType::For]) {
+            return self.node(checkpoint, SyntaxKind::ForStatement, Self::for_statement);
+        }
+        if self.advance_for_token_types(&[TokenType::If]) {
+            return self.node(checkpoint, SyntaxKind::IfStatement, Self::if_statement);
+        }
+        if self.advance_for_token_types(&[TokenType::Print]) {
+            return self.node(
+                checkpoint,
+                SyntaxKind::PrintStatement,
+                Self::print_statement,
+            );
+        }
+        if let Some(keyword) = self.take_for_token_types(&[TokenType::Return]) {
+            return self.node(checkpoint, SyntaxKind::ReturnStatement, |parser| {
+                parser.return_statement(keyword)
+            });
+        }
+        if self.advance_for_token_types(&[TokenType::While]) {
+            return self.node(
+                checkpoint,
+                SyntaxKind::WhileStatement,
+                Self::while_statement,
+            );
+        }
+        if let Some(left_brace) = self.take_for_token_types(&[TokenType::LeftBrace]) {
+            return self.node(checkpoint, SyntaxKind::Block, |parser| {
+                Ok(Statement::new_block(parser.block(left_brace.span)?))
+            });
+        }
+        self.node(
+            checkpoint,
+            SyntaxKind::ExpressionStatement,
+            Self::expression_statement,
+        )
+    }
+
+    // called with the opening brace already consumed
+    fn block(&mut self, left_brace: Span) -> ParseResult<Vec<Statement>> {
+        let mut statements = Vec::new();
+
+        while !self.check_future_for_token(TokenType::RightBrace) && !self.is_at_end() {
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after block.")
+            .map_err(|error| error.with_label(left_brace, "opening '{' here".to_string()))?;
+        Ok(statements)
+    }
+
+    // there is no for node in the AST, the loop is desugared into a block holding
+    // the initializer and a while loop, the CST keeps it as a ForStatement node
+    fn for_statement(&mut self) -> ParseResult<Statement> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
+
+        let checkpoint = self.checkpoint();
+        let initializer = if self.advance_for_token_types(&[TokenType::Semicolon]) {
+            None
+        } else if self.advance_for_token_types(&[TokenType::Var]) {
+            Some(self.node(checkpoint, SyntaxKind::VarDeclaration, |parser| {
+                parser.var_declaration(None)
+            })?)
+        } else {
+            Some(self.node(
+                checkpoint,
+                SyntaxKind::ExpressionStatement,
+                Self::expression_statement,
+            )?)
+        };
+
+        let mut condition = None;
+        if !self.check_future_for_token(TokenType::Semicolon) {
+            condition = Some(self.expression()?);
+        }
+        let condition_end =
+            self.consume(TokenType::Semicolon, "Expect ';' after loop condition.")?;
+
+        let mut increment = None;
+        if !self.check_future_for_token(TokenType::RightParen) {
+            increment = Some(self.expression()?);
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after for clauses.")?;
+
+        let mut body = self.statement()?;
+
+        if let Some(increment) = increment {
+            body = Statement::new_block(vec![body, Statement::new_expression(increment)]);
+        }
+
+        // a missing condition is an implicit "true" where the condition would be
+        let condition = condition
+            .unwrap_or_else(|| Expression::new_literal(Literal::Boolean(true), condition_end.span));
+        body = Statement::new_while(condition, body);
+
+        if let Some(initializer) = initializer {
+            body = Statement::new_block(vec![initializer, body]);
+        }
+
+        Ok(body)
+    }
+
+    fn if_statement(&mut self) -> ParseResult<Statement> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after if condition.")?;
+
+        let then_branch = self.statement()?;
+        let mut else_branch = None;
+        // the else is bound to the nearest if that precedes it
+        if self.advance_for_token_types(&[TokenType::Else]) {
+            else_branch = Some(self.statement()?);
+        }
+
+        Ok(Statement::new_if(condition, then_branch, else_branch))
+    }
+
+    fn return_statement(&mut self, keyword: Token) -> ParseResult<Statement> {
+        let mut value = None;
+        if !self.check_future_for_token(TokenType::Semicolon) {
+            value = Some(self.expression()?);
+        }
+
+        self.consume(TokenType::Semicolon, "Expect ';' after return value.")?;
+        Ok(Statement::new_return(keyword, value))
+    }
+
+    fn while_statement(&mut self) -> ParseResult<Statement> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
+        let body = self.statement()?;
+
+        Ok(Statement::new_while(condition, body))
+    }
+
+    fn print_statement(&mut self) -> ParseResult<Statement> {
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
+        Ok(Statement::new_print(value))
+    }
+
+    fn expression_statement(&mut self) -> ParseResult<Statement> {
+        let expression = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after expression.")?;
+        Ok(Statement::new_expression(expression))
+    }
 }
 
 /*
@@ -89,121 +590,496 @@ Algorithm: Recursive Descent Parsing
 
 */
 
-impl Parser {
-    fn expression(&mut self) -> Expression {
-        self.equality()
+impl Parser<'_> {
+    fn expression(&mut self) -> ParseResult<Expression> {
+        self.nested("Expression nested too deeply.", Self::assignment)
+    }
+
+    fn assignment(&mut self) -> ParseResult<Expression> {
+        let checkpoint = self.checkpoint();
+        let expression = self.or()?;
+
+        if let Some(equals) = self.take_for_token_types(&[TokenType::Equal]) {
+            let value = self.nested("Expression nested too deeply.", Self::assignment)?;
+            self.wrap(checkpoint, SyntaxKind::Assignment);
+
+            match expression {
+                Expression::Variable { name, .. } => {
+                    return Ok(Expression::new_assign(name, Box::new(value)));
+                }
+                Expression::Get { object, name, .. } => {
+                    return Ok(Expression::new_set(object, name, Box::new(value)));
+                }
+                _ => {}
+            }
+
+            // we report the error but don't bail out, the parser isn't confused
+            self.errors.push(
+                ParserError::new(
+                    ErrorCode::InvalidAssignmentTarget,
+                    "Invalid assignment target.".to_string(),
+                    equals,
+                )
+                .with_label(expression.span(), "this can't be assigned to".to_string())
+                .with_help("only variables and fields can be assigned to".to_string()),
+            );
+        }
+
+        Ok(expression)
+    }
+
+    fn or(&mut self) -> ParseResult<Expression> {
+        let checkpoint = self.checkpoint();
+        let mut expression = self.and()?;
+
+        while let Some(operator) = self.take_for_token_types(&[TokenType::Or]) {
+            let right = self.and()?;
+            self.wrap(checkpoint, SyntaxKind::Logical);
+            expression = Expression::new_logical(Box::new(expression), operator, Box::new(right));
+        }
+
+        Ok(expression)
     }
 
-    fn equality(&mut self) -> Expression {
-        let mut expression: Expression = self.comparison();
+    fn and(&mut self) -> ParseResult<Expression> {
+        let checkpoint = self.checkpoint();
+        let mut expression = self.equality()?;
+
+        while let Some(operator) = self.take_for_token_types(&[TokenType::And]) {
+            let right = self.equality()?;
+            self.wrap(checkpoint, SyntaxKind::Logical);
+            expression = Expression::new_logical(Box::new(expression), operator, Box::new(right));
+        }
+
+        Ok(expression)
+    }
 
-        while self.advance_for_token_types(vec![TokenType::BangEqual, TokenType::EqualEqual]) {
-            let operator: Token = self.previous();
-            let right: Expression = self.comparison();
+    fn equality(&mut self) -> ParseResult<Expression> {
+        let checkpoint = self.checkpoint();
+        let mut expression: Expression = self.comparison()?;
+
+        while let Some(operator) =
+            self.take_for_token_types(&[TokenType::BangEqual, TokenType::EqualEqual])
+        {
+            let right: Expression = self.comparison()?;
+            self.wrap(checkpoint, SyntaxKind::Binary);
             expression = Expression::new_binary(Box::new(expression), operator, Box::new(right));
         }
 
-        expression
+        Ok(expression)
     }
 
-    fn comparison(&mut self) -> Expression {
-        let mut expression = self.term();
-        while self.advance_for_token_types(vec![
+    fn comparison(&mut self) -> ParseResult<Expression> {
+        let checkpoint = self.checkpoint();
+        let mut expression = self.term()?;
+        while let Some(operator) = self.take_for_token_types(&[
             TokenType::Greater,
             TokenType::GreaterEqual,
             TokenType::Less,
             TokenType::LessEqual,
         ]) {
-            let operator = self.previous();
-            let right = self.term();
+            let right = self.term()?;
+            self.wrap(checkpoint, SyntaxKind::Binary);
             expression = Expression::new_binary(Box::new(expression), operator, Box::new(right));
         }
-        expression
+        Ok(expression)
     }
 
-    fn term(&mut self) -> Expression {
-        let mut expression = self.factor();
-        while self.advance_for_token_types(vec![TokenType::Minus, TokenType::Plus]) {
-            let operator = self.previous();
-            let right = self.factor();
+    fn term(&mut self) -> ParseResult<Expression> {
+        let checkpoint = self.checkpoint();
+        let mut expression = self.factor()?;
+        while let Some(operator) = self.take_for_token_types(&[TokenType::Minus, TokenType::Plus]) {
+            let right = self.factor()?;
+            self.wrap(checkpoint, SyntaxKind::Binary);
             expression = Expression::new_binary(Box::new(expression), operator, Box::new(right));
         }
-        expression
+        Ok(expression)
     }
 
-    fn factor(&mut self) -> Expression {
-        let mut expression = self.unary();
-        while self.advance_for_token_types(vec![TokenType::Slash, TokenType::Star]) {
-            let operator = self.previous();
-            let right = self.unary();
+    fn factor(&mut self) -> ParseResult<Expression> {
+        let checkpoint = self.checkpoint();
+        let mut expression = self.unary()?;
+        while let Some(operator) = self.take_for_token_types(&[TokenType::Slash, TokenType::Star]) {
+            let right = self.unary()?;
+            self.wrap(checkpoint, SyntaxKind::Binary);
             expression = Expression::new_binary(Box::new(expression), operator, Box::new(right));
         }
-        expression
+        Ok(expression)
     }
 
-    fn unary(&mut self) -> Expression {
-        if self.advance_for_token_types(vec![TokenType::Bang, TokenType::Minus]) {
-            let operator = self.previous();
-            let right = self.unary();
-            return Expression::new_unary(operator, Box::new(right));
+    fn unary(&mut self) -> ParseResult<Expression> {
+        let checkpoint = self.checkpoint();
+        if let Some(operator) = self.take_for_token_types(&[TokenType::Bang, TokenType::Minus]) {
+            let right = self.nested("Expression nested too deeply.", Self::unary)?;
+            self.wrap(checkpoint, SyntaxKind::Unary);
+            return Ok(Expression::new_unary(operator, Box::new(right)));
         }
 
-        self.primary()
-            .unwrap_or(Expression::new_literal(Literal::Nil))
+        self.call()
     }
 
-    fn primary(&mut self) -> Option<Expression> {
-        if self.advance_for_token_types(vec![
+    fn call(&mut self) -> ParseResult<Expression> {
+        let checkpoint = self.checkpoint();
+        let mut expression = self.primary()?;
+
+        loop {
+            let arguments = self.checkpoint();
+            if let Some(left_paren) = self.take_for_token_types(&[TokenType::LeftParen]) {
+                expression = self.finish_call(expression, left_paren.span)?;
+                self.wrap(arguments, SyntaxKind::ArgumentList);
+                self.wrap(checkpoint, SyntaxKind::Call);
+            } else if self.advance_for_token_types(&[TokenType::Dot]) {
+                let name =
+                    self.consume(TokenType::Identifier, "Expect property name after '.'.")?;
+                expression = Expression::new_get(Box::new(expression), name);
+                self.wrap(checkpoint, SyntaxKind::Get);
+            } else {
+                break;
+            }
+        }
+
+        Ok(expression)
+    }
+
+    // called with the opening paren already consumed
+    fn finish_call(&mut self, callee: Expression, left_paren: Span) -> ParseResult<Expression> {
+        let mut arguments = Vec::new();
+        if !self.check_future_for_token(TokenType::RightParen) {
+            loop {
+                if arguments.len() >= MAX_ARGUMENTS {
+                    self.errors.push(ParserError::new(
+                        ErrorCode::TooManyArguments,
+                        format!("Can't have more than {} arguments.", MAX_ARGUMENTS),
+                        self.peek().clone(),
+                    ));
+                }
+                arguments.push(self.expression()?);
+
+                if !self.advance_for_token_types(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
+        let paren = self
+            .consume(TokenType::RightParen, "Expect ')' after arguments.")
+            .map_err(|error| error.with_label(left_paren, "opening '(' here".to_string()))?;
+        Ok(Expression::new_call(Box::new(callee), paren, arguments))
+    }
+
+    fn primary(&mut self) -> ParseResult<Expression> {
+        let checkpoint = self.checkpoint();
+        if let Some(token) = self.take_for_token_types(&[
             TokenType::False,
             TokenType::True,
             TokenType::Nil,
             TokenType::Number,
             TokenType::String,
         ]) {
-            match self.previous().literal.unwrap() {
-                // if the literal is a number, we will return a number expression
-                // with the value of the number
-                // if the literal is a string, we will return a string expression
-                // with the value of the string
-                // if the literal is a boolean, we will return a boolean expression
-                // with the value of the boolean
-                // if the literal is nil, we will return a nil expression
-                // with the value of the nil
-                // if the literal is a identifier, we will return an expression
-                // with the value of the identifier
-                Literal::Number(value) => {
-                    return Some(Expression::new_literal(Literal::Number(value)))
-                }
-                Literal::String(value) => {
-                    return Some(Expression::new_literal(Literal::String(value)))
-                }
-                Literal::Boolean(value) => {
-                    return Some(Expression::new_literal(Literal::Boolean(value)))
-                }
-                Literal::Nil => return Some(Expression::new_literal(Literal::Nil)),
-                Literal::Identifier(value) => {
-                    return Some(Expression::new_literal(Literal::Identifier(value)))
-                }
+            self.wrap(checkpoint, SyntaxKind::Literal);
+            return match token.literal {
+                Some(literal) => Ok(Expression::new_literal(literal, token.span)),
+                // the scanner gives all of these a literal, only tokens made by hand lack it
+                None => Err(ParserError::new(
+                    ErrorCode::ExpectedExpression,
+                    "Expect expression.".to_string(),
+                    token,
+                )),
             };
         }
 
-        if self.advance_for_token_types(vec![TokenType::LeftParen]) {
-            let expression = self.expression();
-            if self.check_future_for_token(TokenType::RightParen) {
-                self.advance();
-                return Some(Expression::new_grouping(expression));
-            }
-            self.errors.push(ParserError {
-                message: "Expect ')' after expression.".to_string(),
-                token: self.peek(),
+        if let Some(first) = self.take_for_token_types(&[TokenType::Interpolation]) {
+            return self.node(checkpoint, SyntaxKind::Interpolation, |parser| {
+                parser.interpolation(first)
             });
         }
 
-        self.errors.push(ParserError {
-            message: "".to_string(),
-            token: self.peek(),
-        });
+        if let Some(keyword) = self.take_for_token_types(&[TokenType::Super]) {
+            self.consume(TokenType::Dot, "Expect '.' after 'super'.")?;
+            let method = self.consume(TokenType::Identifier, "Expect superclass method name.")?;
+            self.wrap(checkpoint, SyntaxKind::Super);
+            return Ok(Expression::new_super(keyword, method));
+        }
 
-        None
+        if let Some(keyword) = self.take_for_token_types(&[TokenType::This]) {
+            self.wrap(checkpoint, SyntaxKind::This);
+            return Ok(Expression::new_this(keyword));
+        }
+
+        if let Some(name) = self.take_for_token_types(&[TokenType::Identifier]) {
+            self.wrap(checkpoint, SyntaxKind::Variable);
+            return Ok(Expression::new_variable(name));
+        }
+
+        if let Some(left_paren) = self.take_for_token_types(&[TokenType::LeftParen]) {
+            let left_paren = left_paren.span;
+            let expression = self.expression()?;
+            let right_paren = self
+                .consume(TokenType::RightParen, "Expect ')' after expression.")
+                .map_err(|error| error.with_label(left_paren, "opening '(' here".to_string()))?;
+            self.wrap(checkpoint, SyntaxKind::Grouping);
+            return Ok(Expression::new_grouping(
+                expression,
+                left_paren.to(right_paren.span),
+            ));
+        }
+
+        Err(ParserError::new(
+            ErrorCode::ExpectedExpression,
+            "Expect expression.".to_string(),
+            self.peek().clone(),
+        ))
+    }
+
+    // "a ${b} c" is scanned as INTERPOLATION "a ", IDENTIFIER b, STRING " c" and
+    // parsed into an Interpolation of the literal "a ", b and the literal " c".
+    fn interpolation(&mut self, first: Token) -> ParseResult<Expression> {
+        let start = first.span;
+        let mut segment = first.span;
+        let mut parts = Vec::new();
+        Self::push_string_segment(&mut parts, first);
+
+        loop {
+            parts.push(self.expression()?);
+
+            let (token, ended) = match self.take_for_token_types(&[TokenType::Interpolation]) {
+                Some(token) => (token, false),
+                None => {
+                    let token = self
+                        .consume(
+                            TokenType::String,
+                            "Expect '}' after interpolated expression.",
+                        )
+                        .map_err(|error| {
+                            error.with_label(segment, "interpolation starts here".to_string())
+                        })?;
+                    (token, true)
+                }
+            };
+
+            segment = token.span;
+            Self::push_string_segment(&mut parts, token);
+
+            if ended {
+                return Ok(Expression::new_interpolation(parts, start.to(segment)));
+            }
+        }
+    }
+
+    // empty segments, as in "${a}${b}", add nothing
+    fn push_string_segment(parts: &mut Vec<Expression>, token: Token) {
+        match token.literal {
+            Some(Literal::String(value)) if value.is_empty() => {}
+            Some(value) => parts.push(Expression::new_literal(value, token.span)),
+            None => {}
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{Lexer, Scanner};
+
+    fn parse(source: &str) -> Result<Program, Vec<ParserError>> {
+        let mut scanner = Scanner::new(source.to_string());
+        scanner.scan_tokens();
+        Parser::new(scanner.tokens).parse()
+    }
+
+    fn parse_errors(source: &str) -> Vec<ParserError> {
+        parse(source).expect_err("expected syntax errors")
+    }
+
+    #[test]
+    fn test_literal_token_without_its_literal() {
+        let tokens = vec![Token::new(
+            TokenType::Number,
+            "1",
+            None,
+            1,
+            1,
+            Span::new(0, 1),
+        )];
+        let errors = Parser::new(tokens).parse_expressions().unwrap_err();
+
+        assert_eq!(errors[0].message, "Expect expression.");
+    }
+
+    #[test]
+    fn test_valid_program_is_parsed() {
+        let program = parse("var a = 1; { print a; } fun f() { return a; }").unwrap();
+
+        assert_eq!(program.statements.len(), 3);
+    }
+
+    #[test]
+    fn test_reports_every_syntax_error() {
+        let errors = parse_errors("var = 1;\nprint (1 + ;\nvar ok = 2;\nprint ok\nfun f() {}");
+        let lines: Vec<u32> = errors.iter().map(|error| error.token.line).collect();
+
+        assert_eq!(lines, vec![1, 2, 5]);
+        assert_eq!(errors[0].message, "Expect variable name.");
+        assert_eq!(errors[2].message, "Expect ';' after value.");
+    }
+
+    #[test]
+    fn test_recovers_inside_blocks() {
+        let errors = parse_errors("{ var a = ; print 1; } print 2 print 3;");
+
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn test_unbalanced_parenthesis_is_a_single_error() {
+        let errors = parse_errors("print (1 + 2;");
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Expect ')' after expression.");
+    }
+
+    #[test]
+    fn test_non_fatal_errors_still_reject_the_program() {
+        let errors = parse_errors("1 = 2;");
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Invalid assignment target.");
+    }
+
+    #[test]
+    fn test_error_messages_match_reference_format() {
+        let messages: Vec<String> = parse_errors("print (72 +);\nprint (1")
+            .iter()
+            .map(|error| error.to_string())
+            .collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "[line 1] Error at ')': Expect expression.",
+                "[line 2] Error at end: Expect ')' after expression.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parses_from_a_lexer() {
+        let tokens = Lexer::new("var a = 1; print a;").map(Result::unwrap);
+        let program = Parser::new(tokens).parse().unwrap();
+
+        assert_eq!(program.statements.len(), 2);
+    }
+
+    #[test]
+    fn test_expression_spans() {
+        let source = "a.b = (1 + -x) * f(\"é\")";
+        let mut scanner = Scanner::new(source.to_string());
+        scanner.scan_tokens();
+        let expression = Parser::new(scanner.tokens)
+            .parse_expressions()
+            .unwrap()
+            .remove(0);
+
+        let text = |span: Span| &source[span.start..span.end];
+        assert_eq!(text(expression.span()), "a.b = (1 + -x) * f(\"é\")");
+
+        let Expression::Set { value, .. } = expression else {
+            panic!("expected a set expression");
+        };
+        let Expression::Binary { left, right, .. } = *value else {
+            panic!("expected a binary expression");
+        };
+        assert_eq!(text(left.span()), "(1 + -x)");
+        assert_eq!(text(right.span()), "f(\"é\")");
+
+        let Expression::Grouping { expression, .. } = *left else {
+            panic!("expected a grouping");
+        };
+        let Expression::Binary { right, .. } = *expression else {
+            panic!("expected a binary expression");
+        };
+        assert_eq!(text(right.span()), "-x");
+    }
+
+    #[test]
+    fn test_interpolation_keeps_its_parts() {
+        let mut scanner = Scanner::new(r#""a ${b}${c} d""#.to_string());
+        scanner.scan_tokens();
+        let expression = Parser::new(scanner.tokens)
+            .parse_expressions()
+            .unwrap()
+            .remove(0);
+
+        assert_eq!(expression.to_string(), "(interpolation a  b c  d)");
+        assert_eq!(expression.span(), Span::new(0, 14));
+
+        let errors = parse_errors(r#"print "a ${b c}";"#);
+        assert_eq!(
+            errors[0].to_string(),
+            "[line 1] Error at 'c': Expect '}' after interpolated expression."
+        );
+    }
+
+    #[test]
+    fn test_unclosed_paren_points_at_opening_paren() {
+        let errors = parse_errors("print (1 + 2;");
+
+        assert_eq!(errors[0].message, "Expect ')' after expression.");
+        assert_eq!(errors[0].labels[0].span, Span::new(6, 7));
+        assert_eq!(errors[0].labels[0].message, "opening '(' here");
+    }
+
+    #[test]
+    fn test_doc_comments_attach_to_declarations() {
+        let program = parse(
+            "/// The answer.\nvar answer = 42;\n/// A point.\nclass Point {\n  /// Makes one.\n  init() {}\n}\n/// Not attached.\nprint 1;",
+        )
+        .unwrap();
+
+        let Statement::Var { doc, .. } = &program.statements[0] else {
+            panic!("expected a var declaration");
+        };
+        assert_eq!(doc.as_deref(), Some("The answer."));
+
+        let Statement::Class { doc, methods, .. } = &program.statements[1] else {
+            panic!("expected a class declaration");
+        };
+        assert_eq!(doc.as_deref(), Some("A point."));
+        assert_eq!(methods[0].doc.as_deref(), Some("Makes one."));
+    }
+
+    #[test]
+    fn test_nesting_too_deeply_is_a_single_error() {
+        fn parse_nested(source: &str) -> Result<Program, Vec<ParserError>> {
+            let mut parser = Parser::new(Lexer::new(source).map(Result::unwrap));
+            parser.max_depth = 8;
+            parser.parse()
+        }
+
+        // a statement and the expression in it take two levels between them
+        assert!(parse_nested(&format!("print {}1{};", "(".repeat(6), ")".repeat(6))).is_ok());
+
+        for (source, message) in [
+            (
+                format!("print {}1{};", "(".repeat(7), ")".repeat(7)),
+                "Expression nested too deeply.",
+            ),
+            (
+                format!("print {}1;", "-".repeat(7)),
+                "Expression nested too deeply.",
+            ),
+            (
+                format!("{}print 1;{}", "{".repeat(8), "}".repeat(8)),
+                "Statement nested too deeply.",
+            ),
+            (
+                format!("{}{}", "fun f() {".repeat(9), "}".repeat(9)),
+                "Statement nested too deeply.",
+            ),
+        ] {
+            let errors = parse_nested(&source).unwrap_err();
+            assert_eq!(errors.len(), 1, "{}", source);
+            assert_eq!(errors[0].code, ErrorCode::NestedTooDeeply);
+            assert_eq!(errors[0].message, message);
+        }
     }
 }
@@ -0,0 +1,255 @@
+use std::io::{BufRead, IsTerminal, Write};
+
+use interpreter_starter_rust::domain::{Expression, Statement, Token, TokenType};
+use interpreter_starter_rust::error::ErrorCode;
+use interpreter_starter_rust::interpreter::Interpreter;
+use interpreter_starter_rust::parser::Parser;
+use interpreter_starter_rust::resolver::Resolver;
+use interpreter_starter_rust::scanner::Lexer;
+
+use crate::cli::{Color, Format};
+use crate::Reporter;
+
+/*
+    The interactive session. Every entry runs against the same interpreter so globals
+    persist, input with unclosed brackets or strings is continued on the next line,
+    and the value of a bare expression is echoed.
+*/
+
+const HELP: &str = "\
+Enter Lox statements or expressions, an expression's value is printed.
+  :history    list the entries of this session
+  :redo       run the previous entry again
+  :redo <n>   run entry <n> again
+  :help       print this help
+  :quit       leave, as does end of input
+";
+
+pub fn run(format: Format, color: Color) {
+    let stdin = std::io::stdin();
+    // prompts would only clutter piped input
+    let interactive = stdin.is_terminal();
+    let mut lines = stdin.lock().lines();
+
+    let mut interpreter = Interpreter::new();
+    let mut history: Vec<String> = Vec::new();
+
+    loop {
+        let mut input = String::new();
+        let mut prompt = "> ";
+
+        loop {
+            if interactive {
+                print!("{}", prompt);
+                let _ = std::io::stdout().flush();
+            }
+
+            let Some(Ok(line)) = lines.next() else {
+                if interactive {
+                    println!();
+                }
+                // an entry cut off by the end of input is still run, so whatever is
+                // left open is reported instead of silently dropped
+                if !input.trim().is_empty() {
+                    evaluate(&mut interpreter, &input, format, color);
+                }
+                return;
+            };
+
+            input.push_str(&line);
+            input.push('\n');
+            if !is_incomplete(&input) {
+                break;
+            }
+            prompt = ". ";
+        }
+
+        let entry = input.trim();
+        match entry {
+            "" => continue,
+            ":quit" => return,
+            ":help" => {
+                print!("{}", HELP);
+                continue;
+            }
+            ":history" => {
+                for (index, entry) in history.iter().enumerate() {
+                    println!("{:>4}  {}", index + 1, entry);
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        let entry = match expand_history(entry, &history) {
+            // show what a :redo stands for before running it
+            Ok(expanded) if expanded != entry => {
+                println!("{}", expanded);
+                expanded
+            }
+            Ok(expanded) => expanded,
+            Err(message) => {
+                eprintln!("{}", message);
+                continue;
+            }
+        };
+
+        evaluate(&mut interpreter, &entry, format, color);
+        history.push(entry);
+    }
+}
+
+// Input is incomplete while a string or block comment is unterminated or a bracket
+// is left open.
+// Stray closing brackets are left for the parser to report.
+pub fn is_incomplete(source: &str) -> bool {
+    let mut unterminated = false;
+    let mut depth = 0;
+    for scanned in Lexer::new(source) {
+        match scanned {
+            Ok(token) => match token.token_type {
+                TokenType::LeftParen | TokenType::LeftBrace => depth += 1,
+                TokenType::RightParen | TokenType::RightBrace => depth -= 1,
+                _ => {}
+            },
+            Err(error) => {
+                unterminated |= matches!(
+                    error.code,
+                    ErrorCode::UnterminatedString
+                        | ErrorCode::UnterminatedBlockComment
+                        | ErrorCode::UnterminatedInterpolation
+                )
+            }
+        }
+    }
+
+    unterminated || depth > 0
+}
+
+// ":redo" is the previous entry and ":redo <n>" the nth, counting from 1
+pub fn expand_history(entry: &str, history: &[String]) -> Result<String, String> {
+    let Some(reference) = entry.strip_prefix(":redo") else {
+        return Ok(entry.to_string());
+    };
+
+    let found = match reference.trim() {
+        "" => history.last(),
+        number => match number.parse::<usize>() {
+            Ok(number) => number.checked_sub(1).and_then(|index| history.get(index)),
+            Err(_) => None,
+        },
+    };
+
+    found
+        .cloned()
+        .ok_or_else(|| format!("No history entry for '{}'.", entry))
+}
+
+// the entry as a single expression, with or without its trailing semicolon
+fn bare_expression(source: &str) -> Option<Expression> {
+    // parsed twice, so the tokens are kept
+    let tokens: Vec<Token> = Lexer::new(source).collect::<Result<_, _>>().ok()?;
+
+    if let Ok(mut program) = Parser::new(tokens.clone()).parse() {
+        return match program.statements.pop() {
+            Some(Statement::Expression(expression)) if program.statements.is_empty() => {
+                Some(expression)
+            }
+            _ => None,
+        };
+    }
+
+    match Parser::new(tokens).parse_expressions() {
+        Ok(mut expressions) if expressions.len() == 1 => expressions.pop(),
+        _ => None,
+    }
+}
+
+fn evaluate(interpreter: &mut Interpreter, source: &str, format: Format, color: Color) {
+    let mut reporter = Reporter::new(format, color, source, "<repl>");
+
+    match bare_expression(source) {
+        Some(expression) => {
+            let statements = [Statement::new_expression(expression)];
+            let mut resolver = Resolver::new(interpreter);
+            resolver.resolve(&statements);
+
+            if !resolver.errors.is_empty() {
+                for error in resolver.errors {
+                    reporter.report(error);
+                }
+            } else if let [Statement::Expression(expression)] = &statements {
+                match interpreter.evaluate(expression) {
+                    Ok(value) => println!("{}", value),
+                    Err(error) => reporter.report(error),
+                }
+            }
+        }
+        None => {
+            if let Err(errors) = interpreter.run(source) {
+                for error in errors {
+                    reporter.report(error);
+                }
+            }
+        }
+    }
+
+    reporter.finish();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_incomplete_input() {
+        assert!(is_incomplete("fun f() {\n"));
+        assert!(is_incomplete("print (1 +\n"));
+        assert!(is_incomplete("print \"multi\nline\n"));
+        assert!(is_incomplete("/* a comment\n"));
+
+        assert!(!is_incomplete("fun f() {}\n"));
+        assert!(!is_incomplete("print 1 +\n"));
+        assert!(!is_incomplete("}\n"));
+        // a brace inside a string or comment doesn't count
+        assert!(!is_incomplete("print \"{\"; // {\n"));
+    }
+
+    #[test]
+    fn test_incomplete_input_is_an_error_at_end_of_input() {
+        for source in [
+            "fun f() {\n",
+            "print (1 +\n",
+            "print \"multi\nline\n",
+            "/* a\n",
+        ] {
+            let errors = Interpreter::new().run(source).unwrap_err();
+            assert!(errors[0].is_compile_error(), "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn test_expand_history() {
+        let history = vec!["var a = 1;".to_string(), "a + 1".to_string()];
+
+        assert_eq!(expand_history(":redo", &history), Ok("a + 1".to_string()));
+        assert_eq!(
+            expand_history(":redo 1", &history),
+            Ok("var a = 1;".to_string())
+        );
+        assert_eq!(expand_history("!a", &history), Ok("!a".to_string()));
+        assert!(expand_history(":redo 3", &history).is_err());
+        assert!(expand_history(":redo x", &history).is_err());
+        assert!(expand_history(":redo", &[]).is_err());
+    }
+
+    #[test]
+    fn test_bare_expressions_are_echoed() {
+        assert!(bare_expression("1 + 2").is_some());
+        assert!(bare_expression("a = 3;").is_some());
+
+        assert!(bare_expression("print 1;").is_none());
+        assert!(bare_expression("1; 2;").is_none());
+        assert!(bare_expression("var a = 1;").is_none());
+    }
+}
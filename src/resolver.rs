@@ -0,0 +1,414 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::domain::expression::ExpressionId;
+use crate::domain::statement::FunctionDeclaration;
+use crate::domain::{token::Token, Expression, Statement};
+use crate::error::ErrorCode;
+use crate::interpreter::Interpreter;
+
+/*
+    The Resolver walks the syntax tree once before it is interpreted. For every
+    variable use it records how many scopes separate it from its declaration,
+    and it reports the errors that can be found without running the program.
+
+    Reference - https://craftinginterpreters.com/resolving-and-binding.html
+*/
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("[line {}] Error{}: {}", .token.line, .token.error_location(), .message)]
+pub struct ResolverError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub token: Token,
+}
+
+impl ResolverError {
+    pub fn new(code: ErrorCode, message: String, token: Token) -> Self {
+        Self {
+            code,
+            message,
+            token,
+        }
+    }
+}
+
+// the kind of function body being resolved, used to validate "return"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+// the kind of class body being resolved, used to validate "this" and "super"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+pub struct Resolver<'a> {
+    interpreter: &'a mut Interpreter,
+    // each scope maps a name to whether its initializer has finished resolving
+    scopes: Vec<HashMap<Rc<str>, bool>>,
+    current_function: FunctionType,
+    current_class: ClassType,
+    pub errors: Vec<ResolverError>,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(interpreter: &'a mut Interpreter) -> Self {
+        Self {
+            interpreter,
+            scopes: Vec::new(),
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn resolve(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.resolve_statement(statement);
+        }
+    }
+
+    fn resolve_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Block(statements) => {
+                self.begin_scope();
+                self.resolve(statements);
+                self.end_scope();
+            }
+            Statement::Class {
+                name,
+                superclass,
+                methods,
+                ..
+            } => {
+                let enclosing_class = self.current_class;
+                self.current_class = ClassType::Class;
+
+                self.declare(name);
+                self.define(name);
+
+                if let Some(superclass) = superclass {
+                    if let Expression::Variable {
+                        name: superclass_name,
+                        ..
+                    } = superclass
+                    {
+                        if superclass_name.symbol == name.symbol {
+                            self.error(
+                                superclass_name,
+                                ErrorCode::InheritsFromItself,
+                                "A class can't inherit from itself.",
+                            );
+                        }
+                    }
+
+                    self.current_class = ClassType::Subclass;
+                    self.resolve_expression(superclass);
+
+                    self.begin_scope();
+                    self.bind("super");
+                }
+
+                self.begin_scope();
+                self.bind("this");
+
+                for method in methods {
+                    let function_type = if &*method.name.symbol == "init" {
+                        FunctionType::Initializer
+                    } else {
+                        FunctionType::Method
+                    };
+                    self.resolve_function(method, function_type);
+                }
+
+                self.end_scope();
+                if superclass.is_some() {
+                    self.end_scope();
+                }
+
+                self.current_class = enclosing_class;
+            }
+            Statement::Expression(expression) | Statement::Print(expression) => {
+                self.resolve_expression(expression);
+            }
+            Statement::Function(declaration) => {
+                // defined before the body so the function can call itself
+                self.declare(&declaration.name);
+                self.define(&declaration.name);
+                self.resolve_function(declaration, FunctionType::Function);
+            }
+            Statement::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.resolve_expression(condition);
+                self.resolve_statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.resolve_statement(else_branch);
+                }
+            }
+            Statement::Return { keyword, value } => {
+                if self.current_function == FunctionType::None {
+                    self.error(
+                        keyword,
+                        ErrorCode::TopLevelReturn,
+                        "Can't return from top-level code.",
+                    );
+                }
+
+                if let Some(value) = value {
+                    if self.current_function == FunctionType::Initializer {
+                        self.error(
+                            keyword,
+                            ErrorCode::ReturnFromInitializer,
+                            "Can't return a value from an initializer.",
+                        );
+                    }
+                    self.resolve_expression(value);
+                }
+            }
+            Statement::Var {
+                name, initializer, ..
+            } => {
+                self.declare(name);
+                if let Some(initializer) = initializer {
+                    self.resolve_expression(initializer);
+                }
+                self.define(name);
+            }
+            Statement::While { condition, body } => {
+                self.resolve_expression(condition);
+                self.resolve_statement(body);
+            }
+        }
+    }
+
+    fn resolve_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Assign {
+                id, name, value, ..
+            } => {
+                self.resolve_expression(value);
+                self.resolve_local(*id, name);
+            }
+            Expression::Binary { left, right, .. } | Expression::Logical { left, right, .. } => {
+                self.resolve_expression(left);
+                self.resolve_expression(right);
+            }
+            Expression::Call {
+                callee, arguments, ..
+            } => {
+                self.resolve_expression(callee);
+                for argument in arguments {
+                    self.resolve_expression(argument);
+                }
+            }
+            Expression::Get { object, .. } => {
+                self.resolve_expression(object);
+            }
+            Expression::Grouping { expression, .. } => {
+                self.resolve_expression(expression);
+            }
+            Expression::Interpolation { parts, .. } => {
+                for part in parts {
+                    self.resolve_expression(part);
+                }
+            }
+            Expression::Literal { .. } => {}
+            Expression::Set { object, value, .. } => {
+                self.resolve_expression(value);
+                self.resolve_expression(object);
+            }
+            Expression::Super { id, keyword, .. } => {
+                match self.current_class {
+                    ClassType::None => {
+                        self.error(
+                            keyword,
+                            ErrorCode::SuperOutsideClass,
+                            "Can't use 'super' outside of a class.",
+                        );
+                    }
+                    ClassType::Class => {
+                        self.error(
+                            keyword,
+                            ErrorCode::SuperWithoutSuperclass,
+                            "Can't use 'super' in a class with no superclass.",
+                        );
+                    }
+                    ClassType::Subclass => {}
+                }
+                self.resolve_local(*id, keyword);
+            }
+            Expression::This { id, keyword, .. } => {
+                if self.current_class == ClassType::None {
+                    self.error(
+                        keyword,
+                        ErrorCode::ThisOutsideClass,
+                        "Can't use 'this' outside of a class.",
+                    );
+                    return;
+                }
+                self.resolve_local(*id, keyword);
+            }
+            Expression::Unary { right, .. } => {
+                self.resolve_expression(right);
+            }
+            Expression::Variable { id, name, .. } => {
+                let declared_but_not_defined =
+                    self.scopes.last().and_then(|scope| scope.get(&name.symbol)) == Some(&false);
+
+                if declared_but_not_defined {
+                    self.error(
+                        name,
+                        ErrorCode::ReadInOwnInitializer,
+                        "Can't read local variable in its own initializer.",
+                    );
+                }
+                self.resolve_local(*id, name);
+            }
+        }
+    }
+
+    fn resolve_function(&mut self, declaration: &FunctionDeclaration, function_type: FunctionType) {
+        let enclosing_function = self.current_function;
+        self.current_function = function_type;
+
+        self.begin_scope();
+        for param in &declaration.params {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve(&declaration.body);
+        self.end_scope();
+
+        self.current_function = enclosing_function;
+    }
+
+    // walks the scopes from innermost outward; variables not found are global
+    fn resolve_local(&mut self, id: ExpressionId, name: &Token) {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if scope.contains_key(&name.symbol) {
+                self.interpreter.resolve(id, depth);
+                return;
+            }
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+
+        if scope.contains_key(&name.symbol) {
+            self.error(
+                name,
+                ErrorCode::AlreadyDeclared,
+                "Already a variable with this name in this scope.",
+            );
+            return;
+        }
+
+        scope.insert(name.symbol.clone(), false);
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.symbol.clone(), true);
+        }
+    }
+
+    // binds a name the interpreter defines implicitly, like "this" and "super"
+    fn bind(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.into(), true);
+        }
+    }
+
+    fn error(&mut self, token: &Token, code: ErrorCode, message: &str) {
+        self.errors
+            .push(ResolverError::new(code, message.to_string(), token.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    fn resolve(source: &str) -> Vec<String> {
+        let mut scanner = Scanner::new(source.to_string());
+        scanner.scan_tokens();
+        let program = Parser::new(scanner.tokens).parse().unwrap();
+
+        let mut interpreter = Interpreter::new();
+        let mut resolver = Resolver::new(&mut interpreter);
+        resolver.resolve(&program.statements);
+
+        resolver
+            .errors
+            .iter()
+            .map(|error| error.message.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_program_has_no_errors() {
+        let errors = resolve(
+            "var a = 1; { var b = a; } fun f(x) { var y = x; return y; }
+             class A { init() { this.x = 1; return; } }
+             class B < A { method() { return super.init(); } }",
+        );
+
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_static_errors() {
+        assert_eq!(
+            resolve("{ var a = 1; { var a = a; } }"),
+            vec!["Can't read local variable in its own initializer."]
+        );
+        assert_eq!(
+            resolve("fun f() { var a = 1; var a = 2; }"),
+            vec!["Already a variable with this name in this scope."]
+        );
+        assert_eq!(
+            resolve("return 1;"),
+            vec!["Can't return from top-level code."]
+        );
+        assert_eq!(
+            resolve("class A { init() { return 1; } }"),
+            vec!["Can't return a value from an initializer."]
+        );
+        assert_eq!(
+            resolve("print this;"),
+            vec!["Can't use 'this' outside of a class."]
+        );
+        assert_eq!(
+            resolve("class A < A {}"),
+            vec!["A class can't inherit from itself."]
+        );
+        assert_eq!(
+            resolve("class A { f() { super.f(); } }"),
+            vec!["Can't use 'super' in a class with no superclass."]
+        );
+    }
+}
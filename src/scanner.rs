@@ -1,52 +1,119 @@
+use std::collections::{HashSet, VecDeque};
+use std::rc::Rc;
+
 use crate::domain::token::Token;
 use crate::domain::token_type::TokenType;
-use crate::domain::Literal;
+use crate::domain::{Literal, Span, Trivia, TriviaKind};
+use crate::error::ErrorCode;
+use crate::unicode;
 
 /*
     The Scanner is responsible for converting the source code into a sequence of tokens.
     The Scanner will read the source code character by character and convert it into tokens.
 
+    The scanning itself is done by the Lexer, an iterator that borrows the source and
+    scans one lexeme at a time as tokens are asked for, so a parser pulling from it
+    never holds more than a few tokens. The Scanner collects everything a Lexer yields
+    for callers that want all tokens and errors up front.
+
     Reference - https://craftinginterpreters.com/scanning.html#recognizing-lexemes
 */
 
 #[derive(Debug, Clone)]
 pub struct Scanner {
-    pub source: Vec<char>,
+    pub source: String,
     pub tokens: Vec<Token>,
-    pub start: usize,
-    pub current: usize,
-    pub line: u32,
-    pub column: u32,
     pub errors: Vec<ScannerError>,
 }
 
 impl Scanner {
     pub fn new(source: String) -> Self {
         Self {
-            source: source.chars().collect(),
+            source,
             tokens: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn scan_tokens(&mut self) {
+        for scanned in Lexer::new(&self.source) {
+            match scanned {
+                Ok(token) => self.tokens.push(token),
+                Err(error) => self.errors.push(error),
+            }
+        }
+    }
+}
+
+// Yields tokens and errors in source order, ending with the Eof token.
+#[derive(Debug, Clone)]
+pub struct Lexer<'src> {
+    source: &'src str,
+    // byte offsets of the start of the current lexeme and of the next character
+    start: usize,
+    current: usize,
+    line: u32,
+    column: u32,
+    // the line and 1-based column the current lexeme starts at
+    start_line: u32,
+    start_column: u32,
+    // trivia waiting to be attached to the next token
+    trivia: Vec<Trivia>,
+    // whether whitespace, plain comments and skipped text are kept as trivia too
+    all_trivia: bool,
+    // the interpolations being scanned, innermost last, with the braces opened inside
+    // each and the error reported if it is never closed
+    interpolations: Vec<(u32, ScannerError)>,
+    // every distinct identifier is allocated once and shared by the tokens spelling
+    // it, punctuation and keywords are always spelled the same and are kept by type
+    symbols: HashSet<Rc<str>>,
+    fixed_lexemes: Vec<Option<Rc<str>>>,
+    // scanning a lexeme can produce a token and errors, they wait here to be yielded
+    scanned: VecDeque<Result<Token, ScannerError>>,
+    finished: bool,
+}
+
+impl<'src> Lexer<'src> {
+    pub fn new(source: &'src str) -> Self {
+        Self {
+            source,
             start: 0,
             current: 0,
             line: 1,
             column: 0,
-            errors: Vec::new(),
+            start_line: 1,
+            start_column: 1,
+            trivia: Vec::new(),
+            all_trivia: false,
+            interpolations: Vec::new(),
+            symbols: HashSet::new(),
+            fixed_lexemes: Vec::new(),
+            scanned: VecDeque::new(),
+            finished: false,
         }
     }
 
-    pub fn scan_tokens(&mut self) {
-        while !self.is_at_end() {
-            // We are at the beginning of the next lexeme.
-            self.start = self.current;
-            Self::scan_token(self);
+    // keeps all trivia, so that the tokens and their trivia cover the source exactly
+    pub fn with_all_trivia(mut self) -> Self {
+        self.all_trivia = true;
+        self
+    }
+
+    fn finish(&mut self) {
+        for (_, error) in self.interpolations.drain(..) {
+            self.scanned.push_back(Err(error));
         }
 
-        self.tokens.push(Token::new(
+        self.scanned.push_back(Ok(Token::new(
             TokenType::Eof,
             "".to_string(),
             None,
             self.line,
-            self.column,
-        ));
+            self.column + 1,
+            Span::new(self.current, self.current),
+        )
+        .with_leading_trivia(std::mem::take(&mut self.trivia))));
+        self.finished = true;
     }
 
     fn is_at_end(&self) -> bool {
@@ -58,8 +125,24 @@ impl Scanner {
         match current_char {
             '(' => Self::add_token(self, TokenType::LeftParen, None),
             ')' => Self::add_token(self, TokenType::RightParen, None),
-            '{' => Self::add_token(self, TokenType::LeftBrace, None),
-            '}' => Self::add_token(self, TokenType::RightBrace, None),
+            '{' => {
+                if let Some((braces, _)) = self.interpolations.last_mut() {
+                    *braces += 1;
+                }
+                Self::add_token(self, TokenType::LeftBrace, None)
+            }
+            // the brace closing an interpolation continues its string
+            '}' => match self.interpolations.last_mut() {
+                Some((0, _)) => {
+                    self.interpolations.pop();
+                    Self::construct_string(self);
+                }
+                Some((braces, _)) => {
+                    *braces -= 1;
+                    Self::add_token(self, TokenType::RightBrace, None)
+                }
+                None => Self::add_token(self, TokenType::RightBrace, None),
+            },
             ',' => Self::add_token(self, TokenType::Comma, None),
             '.' => Self::add_token(self, TokenType::Dot, None),
             '-' => Self::add_token(self, TokenType::Minus, None),
@@ -100,16 +183,19 @@ impl Scanner {
                     while self.peek() != '\n' && !self.is_at_end() {
                         Self::advance(self);
                     }
+                    Self::add_line_comment(self);
+                } else if Self::advance_peek(self, '*') {
+                    Self::skip_block_comment(self);
                 } else {
                     Self::add_token(self, TokenType::Slash, None);
                 }
             }
-            ' ' | '\r' | '\t' => {
-                // Ignore whitespace.
-            }
-            '\n' => {
-                self.line += 1;
-                self.column = 0;
+            ' ' | '\r' | '\t' | '\n' => {
+                // Lines are counted in advance.
+                while matches!(self.peek(), ' ' | '\r' | '\t' | '\n') {
+                    Self::advance(self);
+                }
+                Self::add_trivia(self, TriviaKind::Whitespace);
             }
             '"' => {
                 Self::construct_string(self);
@@ -117,66 +203,129 @@ impl Scanner {
             '0'..='9' => {
                 Self::construct_number(self);
             }
-            'a'..='z' | 'A'..='Z' | '_' => {
+            c if c == '_' || unicode::is_xid_start(c) => {
                 Self::construct_identifier(self);
             }
             _ => {
-                self.errors.push(ScannerError {
-                    message: format!("Unexpected character: {}", current_char),
-                    line: self.line,
-                    column: self.column,
-                });
+                self.error(
+                    ErrorCode::UnexpectedCharacter,
+                    format!("Unexpected character: {}", current_char),
+                );
             }
         }
     }
 
     fn advance(&mut self) -> char {
-        let current_char = self.source[self.current];
-        self.current += 1;
-        self.column += 1;
+        let Some(current_char) = self.source[self.current..].chars().next() else {
+            return '\0';
+        };
+        self.current += current_char.len_utf8();
+
+        if current_char == '\n' {
+            self.line += 1;
+            self.column = 0;
+        } else {
+            self.column += 1;
+        }
 
         current_char
     }
 
     fn advance_peek(&mut self, expected: char) -> bool {
-        if self.is_at_end() {
+        if self.is_at_end() || self.peek() != expected {
             return false;
         }
 
-        let next_char = self.source[self.current];
-        if next_char != expected {
-            return false;
-        }
-
-        self.current += 1;
-        self.column += 1;
+        Self::advance(self);
 
         true
     }
 
-    fn peek(&mut self) -> char {
-        if self.is_at_end() {
-            return '\0';
+    fn peek(&self) -> char {
+        self.source[self.current..].chars().next().unwrap_or('\0')
+    }
+
+    // `///` starts a doc comment, but `////` and longer runs are plain comments
+    fn add_line_comment(&mut self) {
+        let text = &self.source[self.start..self.current];
+        if text.starts_with("///") && !text.starts_with("////") {
+            Self::add_trivia(self, TriviaKind::DocComment);
+        } else {
+            Self::add_trivia(self, TriviaKind::LineComment);
         }
+    }
+
+    // the current lexeme as trivia of the next token, doc comments are always kept
+    fn add_trivia(&mut self, kind: TriviaKind) {
+        if kind == TriviaKind::DocComment || self.all_trivia {
+            self.trivia.push(Trivia::new(
+                kind,
+                self.source[self.start..self.current].to_string(),
+                self.current_span(),
+            ));
+        }
+    }
+
+    // block comments nest, so `/* /* */ */` is a single comment
+    fn skip_block_comment(&mut self) {
+        let mut depth = 1;
+
+        while depth > 0 {
+            if self.is_at_end() {
+                self.error(
+                    ErrorCode::UnterminatedBlockComment,
+                    "Unterminated block comment.".to_string(),
+                );
+                return;
+            }
 
-        self.source[self.current]
+            if self.peek() == '/' && self.peek_next() == '*' {
+                Self::advance(self);
+                depth += 1;
+            } else if self.peek() == '*' && self.peek_next() == '/' {
+                Self::advance(self);
+                depth -= 1;
+            }
+            Self::advance(self);
+        }
+
+        Self::add_trivia(self, TriviaKind::BlockComment);
     }
 
+    // The literal holds the string with its escapes decoded, the lexeme keeps them as
+    // written.
+    // A `${` ends the token as an Interpolation, the tokens of the interpolated
+    // expression follow and the string is picked up again after its closing brace.
     fn construct_string(&mut self) {
+        let mut value = String::new();
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+            let current_char = Self::advance(self);
+            if current_char == '\\' {
+                self.escape_sequence(&mut value);
+            } else if current_char == '$' && self.peek() == '{' {
+                let unterminated = ScannerError {
+                    code: ErrorCode::UnterminatedInterpolation,
+                    message: "Unterminated interpolation.".to_string(),
+                    line: self.line,
+                    start_line: self.line,
+                    column: self.column,
+                    span: Span::new(self.current - 1, self.current + 1),
+                };
+                Self::advance(self);
+                self.interpolations.push((0, unterminated));
+                Self::add_token(self, TokenType::Interpolation, Some(Literal::String(value)));
+                return;
+            } else {
+                value.push(current_char);
             }
-            Self::advance(self);
         }
 
         // Unterminated string.
         if self.is_at_end() {
-            self.errors.push(ScannerError {
-                message: "Unterminated string.".to_string(),
-                line: self.line,
-                column: self.column,
-            });
+            self.error(
+                ErrorCode::UnterminatedString,
+                "Unterminated string.".to_string(),
+            );
             return;
         }
 
@@ -184,52 +333,205 @@ impl Scanner {
         // We need to advance one more time to consume the closing ".
 
         Self::advance(self);
-        let value: String = self.source[self.start + 1..self.current - 1]
-            .iter()
-            .collect();
 
         Self::add_token(self, TokenType::String, Some(Literal::String(value)));
     }
 
+    // called with the backslash consumed, a bad escape is reported where it is and
+    // left out of the value
+    fn escape_sequence(&mut self, value: &mut String) {
+        let start = self.current - 1;
+        let (line, column) = (self.line, self.column);
+
+        // the unterminated string is reported by the caller
+        if self.is_at_end() {
+            return;
+        }
+
+        let escaped = match Self::advance(self) {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '\\' => Some('\\'),
+            '"' => Some('"'),
+            '$' => Some('$'),
+            '0' => Some('\0'),
+            'u' => self.unicode_escape(),
+            _ => None,
+        };
+
+        match escaped {
+            Some(escaped) => value.push(escaped),
+            None => {
+                let text = &self.source[start..self.current];
+                let message = if text.starts_with("\\u") {
+                    format!("Invalid unicode escape '{}'.", text)
+                } else {
+                    format!("Invalid escape sequence '{}'.", text)
+                };
+                self.scanned.push_back(Err(ScannerError {
+                    code: ErrorCode::InvalidEscape,
+                    message,
+                    line,
+                    start_line: line,
+                    column,
+                    span: Span::new(start, self.current),
+                }));
+            }
+        }
+    }
+
+    // `\u{XXXX}` with one to six hex digits naming a unicode scalar value
+    fn unicode_escape(&mut self) -> Option<char> {
+        if !self.advance_peek('{') {
+            return None;
+        }
+
+        let mut digits = String::new();
+        while self.peek().is_ascii_hexdigit() {
+            digits.push(Self::advance(self));
+        }
+
+        if !self.advance_peek('}') || digits.is_empty() || digits.len() > 6 {
+            return None;
+        }
+
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+    }
+
+    // Numbers are decimals with an optional fraction and exponent, or hexadecimal
+    // (`0x1F`) and binary (`0b1010`) integers. Digits may be separated by `_`.
     fn construct_number(&mut self) {
-        while self.peek().is_numeric() {
+        let radix = match (&self.source[self.start..self.current], self.peek()) {
+            ("0", 'x' | 'X') => 16,
+            ("0", 'b' | 'B') => 2,
+            _ => 10,
+        };
+        if radix != 10 {
             Self::advance(self);
+            return self.construct_integer(radix);
         }
 
+        self.digits(10);
+
         // Look for a fractional part.
-        if self.peek() == '.' && self.peek_next().is_numeric() {
+        if self.peek() == '.' && self.peek_next().is_ascii_digit() {
             // Consume the "."
             Self::advance(self);
+            self.digits(10);
+        }
 
-            while self.peek().is_numeric() {
+        if matches!(self.peek(), 'e' | 'E') {
+            Self::advance(self);
+            if matches!(self.peek(), '+' | '-') {
                 Self::advance(self);
             }
+            if !self.peek().is_ascii_digit() {
+                return self.error(
+                    ErrorCode::InvalidNumber,
+                    "Expect digits in exponent.".to_string(),
+                );
+            }
+            self.digits(10);
         }
 
-        let value: f64 = self.source[self.start..self.current]
-            .iter()
-            .collect::<String>()
-            .parse()
-            .unwrap();
+        let text = &self.source[self.start..self.current];
+        if !Self::separators_are_valid(text, 10) {
+            return self.error(
+                ErrorCode::InvalidNumber,
+                "Expect '_' between two digits in number.".to_string(),
+            );
+        }
+
+        match text.replace('_', "").parse() {
+            Ok(value) => Self::add_token(self, TokenType::Number, Some(Literal::Number(value))),
+            Err(_) => self.error(
+                ErrorCode::InvalidNumber,
+                format!("Invalid number '{}'.", text),
+            ),
+        }
+    }
+
+    // called with the `0x` or `0b` prefix consumed
+    fn construct_integer(&mut self, radix: u32) {
+        let base = if radix == 16 { "hexadecimal" } else { "binary" };
+        let digits = self.digits(radix);
+
+        // a digit that doesn't belong to the base, as in `0b102`, would otherwise
+        // start the next token
+        if self.peek().is_ascii_alphanumeric() {
+            while self.peek().is_ascii_alphanumeric() || self.peek() == '_' {
+                Self::advance(self);
+            }
+            return self.error(
+                ErrorCode::InvalidNumber,
+                format!("Invalid digit in {} number.", base),
+            );
+        }
+
+        let text = &self.source[self.start..self.current];
+        if digits.is_empty() {
+            return self.error(
+                ErrorCode::InvalidNumber,
+                format!("Expect {} digits after '{}'.", base, text),
+            );
+        }
+        if !Self::separators_are_valid(text, radix) {
+            return self.error(
+                ErrorCode::InvalidNumber,
+                "Expect '_' between two digits in number.".to_string(),
+            );
+        }
 
+        // folding into a float rather than an integer can't overflow
+        let value = digits
+            .chars()
+            .filter_map(|c| c.to_digit(radix))
+            .fold(0.0, |value, digit| value * radix as f64 + digit as f64);
         Self::add_token(self, TokenType::Number, Some(Literal::Number(value)));
     }
 
-    fn peek_next(&mut self) -> char {
-        if self.current + 1 >= self.source.len() {
-            return '\0';
+    // consumes digits of the given radix and the separators between them
+    fn digits(&mut self, radix: u32) -> String {
+        let mut digits = String::new();
+        while self.peek().is_digit(radix) || self.peek() == '_' {
+            digits.push(Self::advance(self));
         }
+        digits
+    }
+
+    // every `_` has to sit between two digits, so `1_000` is fine but `1_`, `1_.5`,
+    // `1__0` or `0x_1` are not
+    fn separators_are_valid(text: &str, radix: u32) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        chars.iter().enumerate().all(|(index, &c)| {
+            c != '_'
+                || (index > 0
+                    && chars[index - 1].is_digit(radix)
+                    && chars
+                        .get(index + 1)
+                        .is_some_and(|next| next.is_digit(radix)))
+        })
+    }
 
-        self.source[self.current + 1]
+    fn peek_next(&self) -> char {
+        let mut chars = self.source[self.current..].chars();
+        chars.next();
+        chars.next().unwrap_or('\0')
     }
 
+    // The lexeme of an identifier is the text as written and its symbol is the NFC
+    // form, so that names compare equal however they were encoded.
     fn construct_identifier(&mut self) {
-        while self.peek().is_alphanumeric() || self.peek() == '_' {
+        while unicode::is_xid_continue(self.peek()) {
             Self::advance(self);
         }
 
-        let text: String = self.source[self.start..self.current].iter().collect();
-        let token_type = match text.as_str() {
+        let lexeme = &self.source[self.start..self.current];
+        let text = unicode::nfc(lexeme);
+        let token_type = match &*text {
             "and" => TokenType::And,
             "class" => TokenType::Class,
             "else" => TokenType::Else,
@@ -257,34 +559,120 @@ impl Scanner {
             _ => {}
         }
 
-        Self::add_token(self, token_type, literal);
+        if token_type != TokenType::Identifier {
+            let text = self.fixed_lexeme(token_type, &text);
+            return Self::push_token(self, token_type, text, literal);
+        }
+
+        let symbol = self.intern(&text);
+        // the lexeme shares the symbol's text unless normalizing changed it
+        let lexeme = if *symbol == *lexeme {
+            Rc::clone(&symbol)
+        } else {
+            Rc::from(lexeme)
+        };
+        let token = self.token(token_type, lexeme, literal).with_symbol(symbol);
+        self.scanned.push_back(Ok(token));
     }
 
     fn add_token(&mut self, token_type: TokenType, literal: Option<Literal>) {
-        let text = self.source[self.start..self.current].iter().collect();
-        self.tokens.push(Token::new(
+        let text = &self.source[self.start..self.current];
+        let text = match token_type {
+            // strings and numbers rarely repeat, looking them up isn't worth it
+            TokenType::String | TokenType::Interpolation | TokenType::Number => Rc::from(text),
+            _ => self.fixed_lexeme(token_type, text),
+        };
+        Self::push_token(self, token_type, text, literal);
+    }
+
+    fn intern(&mut self, text: &str) -> Rc<str> {
+        if let Some(symbol) = self.symbols.get(text) {
+            return Rc::clone(symbol);
+        }
+        let symbol: Rc<str> = Rc::from(text);
+        self.symbols.insert(Rc::clone(&symbol));
+        symbol
+    }
+
+    fn fixed_lexeme(&mut self, token_type: TokenType, text: &str) -> Rc<str> {
+        let index = token_type as usize;
+        if self.fixed_lexemes.len() <= index {
+            self.fixed_lexemes.resize(index + 1, None);
+        }
+        Rc::clone(self.fixed_lexemes[index].get_or_insert_with(|| Rc::from(text)))
+    }
+
+    fn push_token(&mut self, token_type: TokenType, text: Rc<str>, literal: Option<Literal>) {
+        let token = self.token(token_type, text, literal);
+        self.scanned.push_back(Ok(token));
+    }
+
+    // a token for the current lexeme, taking the trivia found before it
+    fn token(&mut self, token_type: TokenType, text: Rc<str>, literal: Option<Literal>) -> Token {
+        Token::new(
             token_type,
             text,
             literal,
             self.line,
-            self.column,
-        ));
+            self.start_column,
+            self.current_span(),
+        )
+        .with_start_line(self.start_line)
+        .with_leading_trivia(std::mem::take(&mut self.trivia))
+    }
+
+    // the span of the lexeme scanned so far
+    fn current_span(&self) -> Span {
+        Span::new(self.start, self.current)
+    }
+
+    // reports the current lexeme, which becomes skipped trivia rather than a token
+    fn error(&mut self, code: ErrorCode, message: String) {
+        self.scanned.push_back(Err(ScannerError {
+            code,
+            message,
+            line: self.line,
+            start_line: self.start_line,
+            column: self.start_column,
+            span: self.current_span(),
+        }));
+        Self::add_trivia(self, TriviaKind::Skipped);
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+impl Iterator for Lexer<'_> {
+    type Item = Result<Token, ScannerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // whitespace and comments scan to nothing, so keep going until something is
+        // scanned or the source is used up
+        while self.scanned.is_empty() && !self.finished {
+            if self.is_at_end() {
+                self.finish();
+            } else {
+                // We are at the beginning of the next lexeme.
+                self.start = self.current;
+                self.start_line = self.line;
+                self.start_column = self.column + 1;
+                Self::scan_token(self);
+            }
+        }
+
+        self.scanned.pop_front()
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, thiserror::Error)]
+#[error("[line {line}] Error: {message}")]
 pub struct ScannerError {
+    pub code: ErrorCode,
     pub message: String,
+    // the line the error is reported on, where the offending lexeme ends
     pub line: u32,
+    // where the offending lexeme starts
+    pub start_line: u32,
     pub column: u32,
-}
-
-impl ScannerError {}
-
-impl std::fmt::Display for ScannerError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "[line {}] Error: {}", self.line, self.message)
-    }
+    pub span: Span,
 }
 
 #[cfg(test)]
@@ -304,6 +692,42 @@ mod tests {
         assert_eq!(scanner.tokens[3].token_type, TokenType::Eof);
     }
 
+    #[test]
+    fn test_lexer_scans_on_demand() {
+        let mut lexer = Lexer::new("print 1; @");
+
+        let first = lexer.next().unwrap().unwrap();
+        assert_eq!(&*first.lexeme, "print");
+        // nothing past the token asked for has been scanned yet
+        assert_eq!(lexer.current, 5);
+
+        let rest: Vec<_> = lexer
+            .map(|scanned| {
+                scanned
+                    .map(|token| token.token_type)
+                    .map_err(|error| error.message)
+            })
+            .collect();
+        assert_eq!(
+            rest,
+            vec![
+                Ok(TokenType::Number),
+                Ok(TokenType::Semicolon),
+                Err("Unexpected character: @".to_string()),
+                Ok(TokenType::Eof),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lexemes_are_shared() {
+        let tokens: Vec<Token> = Lexer::new("a + a + b").map(Result::unwrap).collect();
+
+        assert!(Rc::ptr_eq(&tokens[0].lexeme, &tokens[2].lexeme));
+        assert!(Rc::ptr_eq(&tokens[1].lexeme, &tokens[3].lexeme));
+        assert!(!Rc::ptr_eq(&tokens[0].lexeme, &tokens[4].lexeme));
+    }
+
     #[test]
     fn test_scan_tokens() {
         let source = "(){},.-+;*".to_string();
@@ -326,7 +750,7 @@ mod tests {
 
     #[test]
     fn test_scan_tokens_for_string() {
-        let source = format!("\"Coolstorm\"");
+        let source = "\"Coolstorm\"".to_string();
         let mut scanner = Scanner::new(source);
         scanner.scan_tokens();
 
@@ -334,13 +758,281 @@ mod tests {
         assert_eq!(scanner.tokens[0].token_type, TokenType::String);
     }
 
+    #[test]
+    fn test_unicode_identifiers() {
+        // precomposed and decomposed spellings of the same name
+        let mut scanner = Scanner::new("\u{e9}a e\u{301}a λ_٣ ٣".to_string());
+        scanner.scan_tokens();
+
+        let lexemes: Vec<_> = scanner
+            .tokens
+            .iter()
+            .map(|token| (token.token_type, &*token.lexeme, &*token.symbol))
+            .collect();
+        assert_eq!(
+            lexemes,
+            vec![
+                (TokenType::Identifier, "\u{e9}a", "\u{e9}a"),
+                (TokenType::Identifier, "e\u{301}a", "\u{e9}a"),
+                (TokenType::Identifier, "λ_٣", "λ_٣"),
+                (TokenType::Eof, "", ""),
+            ]
+        );
+        // both spellings share one interned symbol
+        assert!(Rc::ptr_eq(
+            &scanner.tokens[0].symbol,
+            &scanner.tokens[1].symbol
+        ));
+        assert_eq!(scanner.tokens[1].span, Span::new(4, 8));
+        assert_eq!(scanner.errors[0].message, "Unexpected character: ٣");
+    }
+
+    #[test]
+    fn test_number_literals() {
+        let mut scanner = Scanner::new("0x1F 0B1010 1e-9 1_000_000 2.5E3 0xff_ff".to_string());
+        scanner.scan_tokens();
+
+        assert!(scanner.errors.is_empty());
+        let values: Vec<_> = scanner
+            .tokens
+            .iter()
+            .filter_map(|token| match token.literal {
+                Some(Literal::Number(value)) => Some(value),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(values, vec![31.0, 10.0, 1e-9, 1_000_000.0, 2500.0, 65535.0]);
+        assert_eq!(&*scanner.tokens[3].lexeme, "1_000_000");
+    }
+
+    #[test]
+    fn test_malformed_number_literals() {
+        let message = |source: &str| {
+            let mut scanner = Scanner::new(source.to_string());
+            scanner.scan_tokens();
+            assert_eq!(scanner.tokens.len(), 1, "{} should not be a token", source);
+            scanner.errors[0].message.clone()
+        };
+
+        assert_eq!(message("0x"), "Expect hexadecimal digits after '0x'.");
+        assert_eq!(message("0b"), "Expect binary digits after '0b'.");
+        assert_eq!(message("0b102"), "Invalid digit in binary number.");
+        assert_eq!(message("1e"), "Expect digits in exponent.");
+        assert_eq!(message("1e+"), "Expect digits in exponent.");
+        assert_eq!(message("1_"), "Expect '_' between two digits in number.");
+        assert_eq!(message("1_.5"), "Expect '_' between two digits in number.");
+        assert_eq!(message("0x1F_"), "Expect '_' between two digits in number.");
+        assert_eq!(message("1__0"), "Expect '_' between two digits in number.");
+        assert_eq!(message("0x_1"), "Expect '_' between two digits in number.");
+        assert_eq!(message("0b_1"), "Expect '_' between two digits in number.");
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let source = r#""a\n\t\r\\\"\0\u{48}\u{1F600}""#;
+        let mut scanner = Scanner::new(source.to_string());
+        scanner.scan_tokens();
+
+        assert!(scanner.errors.is_empty());
+        assert_eq!(&*scanner.tokens[0].lexeme, source);
+        assert_eq!(
+            scanner.tokens[0].literal,
+            Some(Literal::String("a\n\t\r\\\"\0H\u{1F600}".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_interpolation_segments() {
+        let mut scanner = Scanner::new(r#""a ${ {b} } c ${"}"} d""#.to_string());
+        scanner.scan_tokens();
+
+        let tokens: Vec<_> = scanner
+            .tokens
+            .iter()
+            .map(|token| (token.token_type, &*token.lexeme))
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                (TokenType::Interpolation, "\"a ${"),
+                (TokenType::LeftBrace, "{"),
+                (TokenType::Identifier, "b"),
+                (TokenType::RightBrace, "}"),
+                (TokenType::Interpolation, "} c ${"),
+                (TokenType::String, "\"}\""),
+                (TokenType::String, "} d\""),
+                (TokenType::Eof, ""),
+            ]
+        );
+        assert_eq!(
+            scanner.tokens[4].literal,
+            Some(Literal::String(" c ".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_unterminated_interpolation() {
+        let mut scanner = Scanner::new("\"a ${b\n".to_string());
+        scanner.scan_tokens();
+
+        assert_eq!(scanner.errors.len(), 1);
+        assert_eq!(scanner.errors[0].message, "Unterminated interpolation.");
+        assert_eq!(scanner.errors[0].column, 4);
+        assert_eq!(scanner.errors[0].span, Span::new(3, 5));
+    }
+
+    #[test]
+    fn test_invalid_escapes() {
+        let mut scanner = Scanner::new(r#""ok\q\u{D800}\u41""#.to_string());
+        scanner.scan_tokens();
+
+        let errors: Vec<_> = scanner
+            .errors
+            .iter()
+            .map(|error| (error.message.as_str(), error.column, error.span))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                ("Invalid escape sequence '\\q'.", 4, Span::new(3, 5)),
+                ("Invalid unicode escape '\\u{D800}'.", 6, Span::new(5, 13)),
+                ("Invalid unicode escape '\\u'.", 14, Span::new(13, 15)),
+            ]
+        );
+        // the string itself is still scanned, without the bad escapes
+        assert_eq!(
+            scanner.tokens[0].literal,
+            Some(Literal::String("ok41".to_string()))
+        );
+    }
+
     #[test]
     fn test_scan_tokens_for_number() {
-        let source = format!("123.45");
+        let source = "123.45".to_string();
         let mut scanner = Scanner::new(source);
         scanner.scan_tokens();
 
         assert_eq!(scanner.tokens.len(), 2);
         assert_eq!(scanner.tokens[0].token_type, TokenType::Number);
     }
+
+    #[test]
+    fn test_token_spans() {
+        let source = "var s = \"é\nü\";\n  s >= 1;".to_string();
+        let mut scanner = Scanner::new(source.clone());
+        scanner.scan_tokens();
+
+        for token in &scanner.tokens {
+            assert_eq!(&source[token.span.start..token.span.end], &*token.lexeme);
+        }
+
+        // the string spans two lines and "é", "ü" are two bytes each
+        assert_eq!(scanner.tokens[3].span, Span::new(8, 15));
+        assert_eq!(scanner.tokens[3].column, 9);
+
+        let greater_equal = &scanner.tokens[6];
+        assert_eq!(greater_equal.token_type, TokenType::GreaterEqual);
+        assert_eq!(greater_equal.span, Span::new(21, 23));
+        assert_eq!((greater_equal.line, greater_equal.column), (3, 5));
+
+        let eof = scanner.tokens.last().unwrap();
+        assert_eq!(eof.span, Span::new(source.len(), source.len()));
+    }
+
+    #[test]
+    fn test_error_spans() {
+        let mut scanner = Scanner::new("1 @ \"open".to_string());
+        scanner.scan_tokens();
+
+        assert_eq!(scanner.errors[0].span, Span::new(2, 3));
+        assert_eq!(scanner.errors[0].column, 3);
+        assert_eq!(scanner.errors[1].span, Span::new(4, 9));
+    }
+
+    #[test]
+    fn test_multi_line_lexemes_start_where_they_begin() {
+        let mut scanner = Scanner::new("var s = \"a\nb\";\n /* open\n\n".to_string());
+        scanner.scan_tokens();
+
+        // the reported line is where the lexeme ends, the start line goes with the column
+        let string = &scanner.tokens[3];
+        assert_eq!(string.token_type, TokenType::String);
+        assert_eq!((string.start_line, string.column, string.line), (1, 9, 2));
+
+        let comment = &scanner.errors[0];
+        assert_eq!(comment.message, "Unterminated block comment.");
+        assert_eq!(
+            (comment.start_line, comment.column, comment.line),
+            (3, 2, 5)
+        );
+    }
+
+    #[test]
+    fn test_block_comments() {
+        let mut scanner = Scanner::new("1 /* a /* nested\n */ comment */ 2\n/* open".to_string());
+        scanner.scan_tokens();
+
+        let types: Vec<TokenType> = scanner
+            .tokens
+            .iter()
+            .map(|token| token.token_type)
+            .collect();
+        assert_eq!(
+            types,
+            vec![TokenType::Number, TokenType::Number, TokenType::Eof]
+        );
+        assert_eq!(scanner.tokens[1].line, 2);
+
+        assert_eq!(scanner.errors.len(), 1);
+        assert_eq!(scanner.errors[0].message, "Unterminated block comment.");
+        assert_eq!(scanner.errors[0].line, 3);
+    }
+
+    #[test]
+    fn test_doc_comments_are_trivia() {
+        let mut scanner =
+            Scanner::new("/// Adds one.\n///\n//// not a doc\n// plain\nfun f() {}".to_string());
+        scanner.scan_tokens();
+
+        let fun = &scanner.tokens[0];
+        assert_eq!(fun.token_type, TokenType::Fun);
+        assert_eq!(fun.leading_trivia.len(), 2);
+        assert_eq!(fun.leading_trivia[0].text, "/// Adds one.");
+        assert_eq!(fun.leading_trivia[0].span, Span::new(0, 13));
+        assert_eq!(fun.doc_comment(), Some("Adds one.\n".to_string()));
+        assert!(scanner.tokens[1].leading_trivia.is_empty());
+    }
+
+    #[test]
+    fn test_all_trivia_is_kept_when_asked_for() {
+        let kinds = |token: &Token| -> Vec<TriviaKind> {
+            token
+                .leading_trivia
+                .iter()
+                .map(|trivia| trivia.kind)
+                .collect()
+        };
+        let scanned: Vec<_> = Lexer::new("/// doc\n// plain\n  /* block */ @print;\n")
+            .with_all_trivia()
+            .collect();
+
+        let print = scanned[1].as_ref().unwrap();
+        assert_eq!(
+            kinds(print),
+            vec![
+                TriviaKind::DocComment,
+                TriviaKind::Whitespace,
+                TriviaKind::LineComment,
+                TriviaKind::Whitespace,
+                TriviaKind::BlockComment,
+                TriviaKind::Whitespace,
+                TriviaKind::Skipped,
+            ]
+        );
+        assert_eq!(print.leading_trivia[6].text, "@");
+        assert_eq!(print.doc_comment(), Some("doc".to_string()));
+
+        let eof = scanned.last().unwrap().as_ref().unwrap();
+        assert_eq!(kinds(eof), vec![TriviaKind::Whitespace]);
+    }
 }
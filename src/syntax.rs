@@ -0,0 +1,323 @@
+use crate::domain::statement::Program;
+use crate::domain::{Span, Token, TokenType};
+use crate::error::LoxError;
+use crate::parser::Parser;
+use crate::scanner::Lexer;
+
+/*
+    The concrete syntax tree keeps every token the parser consumed, each with all of
+    the trivia before it, so the exact source can be written back out of it. Nodes
+    only group tokens, what they mean is still decided by the parser, which builds
+    the Program alongside the tree.
+
+    Code that failed to parse ends up in Error nodes and text that failed to scan in
+    Skipped trivia, so even a broken file round-trips.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxKind {
+    Program,
+    // a declaration or statement that failed to parse, with the tokens skipped after it
+    Error,
+
+    ClassDeclaration,
+    FunctionDeclaration,
+    Method,
+    ParameterList,
+    VarDeclaration,
+
+    Block,
+    ExpressionStatement,
+    ForStatement,
+    IfStatement,
+    PrintStatement,
+    ReturnStatement,
+    WhileStatement,
+
+    Assignment,
+    Logical,
+    Binary,
+    Unary,
+    Call,
+    ArgumentList,
+    Get,
+    Grouping,
+    Literal,
+    Interpolation,
+    Variable,
+    This,
+    Super,
+}
+
+#[derive(Debug, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(Token),
+}
+
+#[derive(Debug, Clone)]
+pub struct SyntaxNode {
+    pub kind: SyntaxKind,
+    pub children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    pub fn new(kind: SyntaxKind, children: Vec<SyntaxElement>) -> Self {
+        Self { kind, children }
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    // every token under this node, in source order
+    pub fn tokens(&self) -> Vec<&Token> {
+        let mut tokens = Vec::new();
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens<'a>(&'a self, tokens: &mut Vec<&'a Token>) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.collect_tokens(tokens),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+    }
+
+    pub fn first_token(&self) -> Option<&Token> {
+        self.children.iter().find_map(|child| match child {
+            SyntaxElement::Node(node) => node.first_token(),
+            SyntaxElement::Token(token) => Some(token),
+        })
+    }
+
+    pub fn last_token(&self) -> Option<&Token> {
+        self.children.iter().rev().find_map(|child| match child {
+            SyntaxElement::Node(node) => node.last_token(),
+            SyntaxElement::Token(token) => Some(token),
+        })
+    }
+
+    // from the first to the last token, leaving out the trivia before the first one
+    pub fn span(&self) -> Option<Span> {
+        let first = self.first_token()?;
+        let last = self.last_token()?;
+        Some(first.span.to(last.span))
+    }
+
+    pub fn write_text(&self, text: &mut String) {
+        for token in self.tokens() {
+            for trivia in token.leading_trivia.iter() {
+                text.push_str(&trivia.text);
+            }
+            text.push_str(&token.lexeme);
+        }
+    }
+}
+
+// Collects the tree bottom up: tokens are appended as they are consumed and a node
+// is made by wrapping everything appended since a checkpoint.
+#[derive(Debug, Default)]
+pub struct TreeBuilder {
+    children: Vec<SyntaxElement>,
+}
+
+impl TreeBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn token(&mut self, token: Token) {
+        self.children.push(SyntaxElement::Token(token));
+    }
+
+    pub fn checkpoint(&self) -> usize {
+        self.children.len()
+    }
+
+    pub fn wrap(&mut self, checkpoint: usize, kind: SyntaxKind) {
+        let children = self.children.split_off(checkpoint);
+        self.children
+            .push(SyntaxElement::Node(SyntaxNode::new(kind, children)));
+    }
+
+    pub fn finish(mut self) -> SyntaxNode {
+        self.wrap(0, SyntaxKind::Program);
+        match self.children.pop() {
+            Some(SyntaxElement::Node(root)) => root,
+            _ => unreachable!("wrap always leaves a node"),
+        }
+    }
+}
+
+pub struct SyntaxTree {
+    pub source: String,
+    pub root: SyntaxNode,
+    // scanner errors first, then parser errors
+    pub errors: Vec<LoxError>,
+    // only when there were no errors
+    pub program: Option<Program>,
+}
+
+impl SyntaxTree {
+    pub fn parse(source: &str) -> Self {
+        let mut errors: Vec<LoxError> = Vec::new();
+        let mut parser_errors = Vec::new();
+
+        let tokens = Lexer::new(source).with_all_trivia().filter_map(|scanned| {
+            scanned
+                .map_err(|error| errors.push(LoxError::from(error)))
+                .ok()
+        });
+        let (parsed, root) = Parser::new(tokens).parse_with_syntax_tree();
+
+        let program = match parsed {
+            Ok(program) => Some(program),
+            Err(parsed_errors) => {
+                parser_errors = parsed_errors;
+                None
+            }
+        };
+        errors.extend(parser_errors.into_iter().map(LoxError::from));
+
+        Self {
+            source: source.to_string(),
+            root,
+            program: program.filter(|_| errors.is_empty()),
+            errors,
+        }
+    }
+
+    // the source again, rebuilt from the tree
+    pub fn text(&self) -> String {
+        let mut text = String::with_capacity(self.source.len());
+        self.root.write_text(&mut text);
+        text
+    }
+
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    // the Eof token closing the tree holds the trivia at the end of the file
+    pub fn eof(&self) -> Option<&Token> {
+        self.root
+            .last_token()
+            .filter(|token| token.token_type == TokenType::Eof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(node: &SyntaxNode) -> Vec<SyntaxKind> {
+        node.nodes().map(|node| node.kind).collect()
+    }
+
+    #[test]
+    fn test_round_trips_source() {
+        let sources = [
+            "",
+            "print 1;",
+            "  // leading comment\n\nvar a = 1; /* block /* nested */ */ print a;\n\n// trailing",
+            "/// Documented.\nclass A < B {\n\tinit(x, y) { this.x = x; }\r\n}\n",
+            "for (var i = 0; i < 3; i = i + 1) { if (i > 1) print \"${i} and ${-i}\"; else { print -i; } }",
+            "fun f() { return super.g(a)(b).c; }  ",
+            "var é = \"e\u{301}\"; print é;",
+            "var e\u{301} = 1; print é;",
+        ];
+
+        for source in sources {
+            let tree = SyntaxTree::parse(source);
+            assert_eq!(tree.text(), source);
+        }
+    }
+
+    #[test]
+    fn test_round_trips_source_with_errors() {
+        let sources = [
+            "var = 1;\nprint (1 + ;\nvar ok = 2; @ # print ok\nfun f() {}",
+            "print \"unterminated",
+            "{ var a = ; print 1; } print 2 print 3; /* unterminated",
+            "class { x",
+            "print 0x;",
+        ];
+
+        for source in sources {
+            let tree = SyntaxTree::parse(source);
+            assert!(tree.has_errors(), "{}", source);
+            assert!(tree.program.is_none());
+            assert_eq!(tree.text(), source);
+        }
+    }
+
+    #[test]
+    fn test_tokens_and_trivia_tile_the_source() {
+        let source = "var a = 1; // one\n/* two */ print a  ;\n";
+        let tree = SyntaxTree::parse(source);
+
+        let mut offset = 0;
+        for token in tree.root.tokens() {
+            for trivia in token.leading_trivia.iter() {
+                assert_eq!(trivia.span.start, offset);
+                offset = trivia.span.end;
+            }
+            assert_eq!(token.span.start, offset);
+            offset = token.span.end;
+        }
+        assert_eq!(offset, source.len());
+        assert!(tree.eof().is_some());
+    }
+
+    #[test]
+    fn test_node_kinds() {
+        let tree = SyntaxTree::parse("var a = 1 + 2 * 3;\nclass A { m(x) {} }\na.b = f(1)(2);\n");
+        assert!(tree.program.is_some());
+
+        assert_eq!(
+            kinds(&tree.root),
+            vec![
+                SyntaxKind::VarDeclaration,
+                SyntaxKind::ClassDeclaration,
+                SyntaxKind::ExpressionStatement,
+            ]
+        );
+
+        let var = tree.root.nodes().next().unwrap();
+        let sum = var.nodes().next().unwrap();
+        assert_eq!(sum.kind, SyntaxKind::Binary);
+        assert_eq!(kinds(sum), vec![SyntaxKind::Literal, SyntaxKind::Binary]);
+
+        let class = tree.root.nodes().nth(1).unwrap();
+        let method = class.nodes().next().unwrap();
+        assert_eq!(method.kind, SyntaxKind::Method);
+        assert_eq!(
+            kinds(method),
+            vec![SyntaxKind::ParameterList, SyntaxKind::Block]
+        );
+
+        let statement = tree.root.nodes().nth(2).unwrap();
+        let assignment = statement.nodes().next().unwrap();
+        assert_eq!(assignment.kind, SyntaxKind::Assignment);
+        assert_eq!(kinds(assignment), vec![SyntaxKind::Get, SyntaxKind::Call]);
+        let span = assignment.span().unwrap();
+        assert_eq!(&tree.source[span.start..span.end], "a.b = f(1)(2)");
+    }
+
+    #[test]
+    fn test_failed_declarations_become_error_nodes() {
+        let tree = SyntaxTree::parse("var = 1;\nprint 2;");
+
+        assert_eq!(
+            kinds(&tree.root),
+            vec![SyntaxKind::Error, SyntaxKind::PrintStatement]
+        );
+        assert_eq!(tree.errors.len(), 1);
+    }
+}
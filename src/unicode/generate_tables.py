@@ -0,0 +1,145 @@
+#!/usr/bin/env python3
+"""
+Generates tables.rs from the Unicode database bundled with Python:
+
+    python3 src/unicode/generate_tables.py > src/unicode/tables.rs
+    python3 src/unicode/generate_tables.py --normalization-test \
+        > src/unicode/normalization_test.txt
+
+XID_Start and XID_Continue are read through str.isidentifier, which Python
+defines in terms of exactly those properties.
+
+The normalization test data has the format of the Unicode NormalizationTest.txt:
+its specific cases and, for every character with a canonical decomposition, its
+character by character line. Hangul syllables are sampled rather than listed.
+"""
+
+import sys
+import unicodedata
+
+# Hangul syllables are composed and decomposed algorithmically
+HANGUL = range(0xAC00, 0xAC00 + 11172)
+
+
+def ranges(predicate):
+    result = []
+    for code in range(sys.maxunicode + 1):
+        if 0xD800 <= code <= 0xDFFF or not predicate(chr(code)):
+            continue
+        if result and result[-1][1] == code - 1:
+            result[-1][1] = code
+        else:
+            result.append([code, code])
+    return result
+
+
+def char(code):
+    return "'\\u{%x}'" % code
+
+
+def canonical_decomposition(c):
+    decomposition = unicodedata.decomposition(c)
+    if not decomposition or decomposition.startswith("<"):
+        return None
+    return [int(code, 16) for code in decomposition.split()]
+
+
+def main():
+    xid_start = ranges(lambda c: c != "_" and c.isidentifier())
+    xid_continue = ranges(lambda c: ("a" + c).isidentifier())
+
+    classes = []
+    for code in range(sys.maxunicode + 1):
+        if 0xD800 <= code <= 0xDFFF:
+            continue
+        combining = unicodedata.combining(chr(code))
+        if not combining:
+            continue
+        if classes and classes[-1][1] == code - 1 and classes[-1][2] == combining:
+            classes[-1][1] = code
+        else:
+            classes.append([code, code, combining])
+
+    decompositions = []
+    compositions = []
+    for code in range(sys.maxunicode + 1):
+        if 0xD800 <= code <= 0xDFFF or code in HANGUL:
+            continue
+        c = chr(code)
+        decomposition = canonical_decomposition(c)
+        if decomposition is None:
+            continue
+        decompositions.append((code, decomposition))
+        # characters excluded from composition don't survive normalization
+        if len(decomposition) == 2 and unicodedata.normalize("NFC", c) == c:
+            compositions.append((tuple(decomposition), code))
+    compositions.sort()
+
+    out = sys.stdout
+    out.write("// Generated by generate_tables.py from Unicode %s, do not edit.\n\n"
+              % unicodedata.unidata_version)
+    out.write("pub const UNICODE_VERSION: &str = \"%s\";\n\n" % unicodedata.unidata_version)
+
+    for name, table in (("XID_START", xid_start), ("XID_CONTINUE", xid_continue)):
+        out.write("pub const %s: &[(char, char)] = &[\n" % name)
+        for low, high in table:
+            out.write("    (%s, %s),\n" % (char(low), char(high)))
+        out.write("];\n\n")
+
+    out.write("pub const COMBINING_CLASSES: &[(char, char, u8)] = &[\n")
+    for low, high, combining in classes:
+        out.write("    (%s, %s, %d),\n" % (char(low), char(high), combining))
+    out.write("];\n\n")
+
+    out.write("pub const DECOMPOSITIONS: &[(char, &[char])] = &[\n")
+    for code, decomposition in decompositions:
+        out.write("    (%s, &[%s]),\n" % (char(code), ", ".join(map(char, decomposition))))
+    out.write("];\n\n")
+
+    out.write("pub const COMPOSITIONS: &[((char, char), char)] = &[\n")
+    for (first, second), code in compositions:
+        out.write("    ((%s, %s), %s),\n" % (char(first), char(second), char(code)))
+    out.write("];\n")
+
+
+# the specific cases of Part 0 of NormalizationTest.txt
+SPECIFIC_CASES = [
+    "1E0A", "1E0C", "1E0A 0323", "1E0C 0307", "0044 0307 0323", "0044 0323 0307",
+    "1E0A 031B", "1E0C 031B", "1E0A 031B 0323", "1E0C 031B 0307",
+    "0044 031B 0307 0323", "0044 031B 0323 0307",
+    "00C8", "0112", "0045 0300", "0045 0304", "1E14", "0112 0300", "1E14 0304",
+    "0045 0304 0300", "0045 0300 0304",
+    "05B8 05B9 05B1 0591 05C3 05B0 05AC 059F",
+    "0592 05B7 05BC 05A5 05B0 05C0 05C4 05AD",
+    "1100 AC00 11A8", "1100 AC00 11A8 11A8",
+]
+
+
+def normalization_test():
+    def line(text):
+        forms = [text] + [unicodedata.normalize(form, text)
+                          for form in ("NFC", "NFD", "NFKC", "NFKD")]
+        return "".join(" ".join("%04X" % ord(c) for c in form) + ";" for form in forms)
+
+    out = sys.stdout
+    out.write("# Generated by generate_tables.py from Unicode %s, do not edit.\n"
+              % unicodedata.unidata_version)
+    out.write("# source;NFC;NFD;NFKC;NFKD; as in NormalizationTest.txt\n")
+
+    out.write("@Part0 # Specific cases\n")
+    for case in SPECIFIC_CASES:
+        out.write(line("".join(chr(int(code, 16)) for code in case.split())) + "\n")
+
+    out.write("@Part1 # Character by character test\n")
+    for code in range(sys.maxunicode + 1):
+        if 0xD800 <= code <= 0xDFFF or (code in HANGUL and (code - HANGUL.start) % 97):
+            continue
+        c = chr(code)
+        if unicodedata.normalize("NFD", c) != c:
+            out.write(line(c) + "\n")
+
+
+if sys.argv[1:] == ["--normalization-test"]:
+    normalization_test()
+else:
+    main()
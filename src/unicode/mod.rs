@@ -0,0 +1,251 @@
+use std::borrow::Cow;
+
+mod tables;
+
+pub use tables::UNICODE_VERSION;
+
+/*
+    The Unicode properties identifiers are defined by. Identifiers start with an
+    XID_Start character or `_` and continue with XID_Continue characters, as in
+    Rust and Python, and names are compared in Normalization Form C so that
+    `é` written precomposed or as `e` and a combining accent is the same name.
+
+    The tables, and the normalization test data, are generated by generate_tables.py.
+
+    Reference - https://www.unicode.org/reports/tr31/ and https://www.unicode.org/reports/tr15/
+*/
+
+pub fn is_xid_start(c: char) -> bool {
+    if c.is_ascii() {
+        return c.is_ascii_alphabetic();
+    }
+    in_ranges(tables::XID_START, c)
+}
+
+pub fn is_xid_continue(c: char) -> bool {
+    if c.is_ascii() {
+        return c.is_ascii_alphanumeric() || c == '_';
+    }
+    in_ranges(tables::XID_CONTINUE, c)
+}
+
+fn in_ranges(ranges: &[(char, char)], c: char) -> bool {
+    ranges
+        .binary_search_by(|&(low, high)| {
+            if high < c {
+                std::cmp::Ordering::Less
+            } else if low > c {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+// Normalization Form C: the canonical decomposition, with combining marks in
+// canonical order, composed again
+pub fn nfc(text: &str) -> Cow<'_, str> {
+    if text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+
+    let mut chars = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        decompose(c, &mut chars);
+    }
+    reorder(&mut chars);
+    Cow::Owned(compose(&chars).into_iter().collect())
+}
+
+fn combining_class(c: char) -> u8 {
+    if c.is_ascii() {
+        return 0;
+    }
+    tables::COMBINING_CLASSES
+        .binary_search_by(|&(low, high, _)| {
+            if high < c {
+                std::cmp::Ordering::Less
+            } else if low > c {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .map_or(0, |index| tables::COMBINING_CLASSES[index].2)
+}
+
+// Hangul syllables are built from leading consonant, vowel and optional trailing
+// consonant jamo, and are composed arithmetically rather than through the tables
+const S_BASE: u32 = 0xAC00;
+const L_BASE: u32 = 0x1100;
+const V_BASE: u32 = 0x1161;
+const T_BASE: u32 = 0x11A7;
+const L_COUNT: u32 = 19;
+const V_COUNT: u32 = 21;
+const T_COUNT: u32 = 28;
+const S_COUNT: u32 = L_COUNT * V_COUNT * T_COUNT;
+
+fn decompose(c: char, out: &mut Vec<char>) {
+    let code = c as u32;
+    if (S_BASE..S_BASE + S_COUNT).contains(&code) {
+        let index = code - S_BASE;
+        let jamo = [
+            L_BASE + index / (V_COUNT * T_COUNT),
+            V_BASE + index % (V_COUNT * T_COUNT) / T_COUNT,
+            T_BASE + index % T_COUNT,
+        ];
+        // a syllable without a trailing consonant has T_BASE as its last jamo
+        let len = if jamo[2] == T_BASE { 2 } else { 3 };
+        out.extend(jamo[..len].iter().filter_map(|&code| char::from_u32(code)));
+        return;
+    }
+
+    match tables::DECOMPOSITIONS.binary_search_by_key(&c, |&(c, _)| c) {
+        Ok(index) => {
+            for &part in tables::DECOMPOSITIONS[index].1 {
+                decompose(part, out);
+            }
+        }
+        Err(_) => out.push(c),
+    }
+}
+
+// sorts every run of combining marks by combining class, keeping the order of
+// marks of the same class
+fn reorder(chars: &mut [char]) {
+    for i in 1..chars.len() {
+        let mut j = i;
+        while j > 0 {
+            let class = combining_class(chars[j]);
+            if class == 0 || combining_class(chars[j - 1]) <= class {
+                break;
+            }
+            chars.swap(j - 1, j);
+            j -= 1;
+        }
+    }
+}
+
+fn compose(chars: &[char]) -> Vec<char> {
+    let mut composed: Vec<char> = Vec::with_capacity(chars.len());
+    // the last starter and the class of the last mark after it, a mark is blocked
+    // from combining with the starter by a mark of the same or a higher class
+    let mut starter: Option<usize> = None;
+    let mut last_class: Option<u8> = None;
+
+    for &c in chars {
+        let class = combining_class(c);
+
+        if let Some(index) = starter {
+            let blocked = last_class.is_some_and(|last| last == 0 || last >= class);
+            if !blocked {
+                if let Some(pair) = compose_pair(composed[index], c) {
+                    composed[index] = pair;
+                    continue;
+                }
+            }
+        }
+
+        if class == 0 {
+            starter = Some(composed.len());
+            last_class = None;
+        } else {
+            last_class = Some(class);
+        }
+        composed.push(c);
+    }
+
+    composed
+}
+
+fn compose_pair(first: char, second: char) -> Option<char> {
+    let (first_code, second_code) = (first as u32, second as u32);
+
+    if (L_BASE..L_BASE + L_COUNT).contains(&first_code)
+        && (V_BASE..V_BASE + V_COUNT).contains(&second_code)
+    {
+        let index = (first_code - L_BASE) * V_COUNT + (second_code - V_BASE);
+        return char::from_u32(S_BASE + index * T_COUNT);
+    }
+    if (S_BASE..S_BASE + S_COUNT).contains(&first_code)
+        && (first_code - S_BASE) % T_COUNT == 0
+        && (T_BASE + 1..T_BASE + T_COUNT).contains(&second_code)
+    {
+        return char::from_u32(first_code + second_code - T_BASE);
+    }
+
+    tables::COMPOSITIONS
+        .binary_search_by_key(&(first, second), |&(pair, _)| pair)
+        .ok()
+        .map(|index| tables::COMPOSITIONS[index].1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identifier_characters() {
+        assert!(is_xid_start('a'));
+        assert!(is_xid_start('é'));
+        assert!(is_xid_start('λ'));
+        assert!(is_xid_start('变'));
+        assert!(!is_xid_start('_'));
+        assert!(!is_xid_start('1'));
+        assert!(!is_xid_start('٣'));
+        assert!(!is_xid_start('😀'));
+
+        assert!(is_xid_continue('_'));
+        assert!(is_xid_continue('1'));
+        assert!(is_xid_continue('٣'));
+        assert!(is_xid_continue('\u{301}'));
+        assert!(!is_xid_continue('-'));
+        assert!(!is_xid_continue('\0'));
+    }
+
+    #[test]
+    fn test_nfc() {
+        // composed from a combining accent
+        assert_eq!(nfc("e\u{301}te\u{301}"), "été");
+        // already composed text is unchanged
+        assert_eq!(nfc("été"), "été");
+        // marks are reordered by class before composing, dot below (220) before
+        // circumflex (230)
+        assert_eq!(nfc("a\u{302}\u{323}"), "\u{1ead}");
+        // a mark of the same class blocks the second one
+        assert_eq!(nfc("a\u{301}\u{301}"), "\u{e1}\u{301}");
+        // singletons decompose and never recompose, the ohm sign becomes omega
+        assert_eq!(nfc("\u{2126}"), "\u{3a9}");
+        // hangul jamo compose into a syllable
+        assert_eq!(nfc("\u{1112}\u{1161}\u{11ab}"), "한");
+    }
+
+    // the NFC invariants of NormalizationTest.txt, for columns c1 to c5:
+    // c2 == NFC(c1) == NFC(c2) == NFC(c3) and c4 == NFC(c4) == NFC(c5)
+    #[test]
+    fn test_nfc_conformance() {
+        let decode = |field: &str| -> String {
+            field
+                .split(' ')
+                .map(|code| u32::from_str_radix(code, 16).ok().and_then(char::from_u32))
+                .collect::<Option<String>>()
+                .unwrap_or_else(|| panic!("bad code point in {:?}", field))
+        };
+
+        let data = include_str!("normalization_test.txt");
+        let cases = data.lines().filter(|line| !line.starts_with(['#', '@']));
+        for line in cases {
+            let columns: Vec<String> = line.split(';').take(5).map(decode).collect();
+            let [c1, c2, c3, c4, c5] = &columns[..] else {
+                panic!("expected five columns in {:?}", line);
+            };
+
+            assert_eq!(nfc(c1), *c2, "NFC(c1) in {}", line);
+            assert_eq!(nfc(c2), *c2, "NFC(c2) in {}", line);
+            assert_eq!(nfc(c3), *c2, "NFC(c3) in {}", line);
+            assert_eq!(nfc(c4), *c4, "NFC(c4) in {}", line);
+            assert_eq!(nfc(c5), *c4, "NFC(c5) in {}", line);
+        }
+    }
+}